@@ -1,16 +1,30 @@
-use std::f32::consts::PI;
-
+use rand::Rng;
+use serde::Deserialize;
 use specs::{Component, VecStorage};
 use specs_derive::Component;
 
-use crate::{remotes::RemoteInput, render::renderer};
+use crate::{
+    remotes::RemoteInput,
+    render::renderer,
+    state::{Block, Weapon},
+};
 
 const PLAYER_BLOCKS_PER_SECOND: f64 = 0.5;
 const BULLET_BLOCKS_PER_SECOND: f64 = 1.;
 const DEFAULT_PLAYER_RADIUS: f64 = 0.1;
 const DEFAULT_BULLET_RADIUS: f64 = 0.05;
-const DEFAULT_HIT_POINTS_NUMBER: u32 = 20;
+const DEFAULT_PICKUP_RADIUS: f64 = 0.15;
+const DEFAULT_MINE_RADIUS: f64 = 0.2;
 const NUMBER_OF_FRAMES_BETWEEN_SHOTS: u32 = 15;
+const NUMBER_OF_FRAMES_TO_RELOAD: u32 = 60;
+const DEFAULT_MAGAZINE_SIZE: u32 = 5;
+const DEFAULT_RESERVE_AMMO: u32 = 20;
+const EFFECT_DURATION_IN_SECONDS: u32 = 10;
+const SPEED_BOOST_FACTOR: f64 = 1.5;
+const RAPID_FIRE_FACTOR: u32 = 3;
+const BOUNCING_AMMO_EXTRA_BOUNCES: u32 = 3;
+const DASH_SPEED_FACTOR: f64 = 3.;
+const FRAMES_UNTIL_MINE_IS_ARMED: u32 = renderer::FRAME_PER_SECOND;
 
 #[derive(Component, Clone)]
 #[storage(VecStorage)]
@@ -25,8 +39,12 @@ impl Position {
     }
 
     pub fn next(&mut self, movement: &Movement) -> Position {
-        let x = self.x + movement.speed * movement.direction.cos();
-        let y = self.y + movement.speed * movement.direction.sin();
+        self.next_slowed(movement, 1.)
+    }
+
+    pub fn next_slowed(&self, movement: &Movement, speed_factor: f64) -> Position {
+        let x = self.x + speed_factor * movement.speed * movement.direction.cos();
+        let y = self.y + speed_factor * movement.speed * movement.direction.sin();
         Position { x, y }
     }
 
@@ -34,6 +52,10 @@ impl Position {
         self.x = new_position.x;
         self.y = new_position.y;
     }
+
+    pub fn block(&self) -> Block {
+        Block(self.x as u8, self.y as u8)
+    }
 }
 
 #[derive(Component)]
@@ -58,35 +80,67 @@ impl Movement {
         }
     }
 
-    pub fn set_player_direction(&mut self, direction: f64) {
+    pub fn set_player_direction(&mut self, direction: f64, speed_factor: f64) {
         self.direction = direction;
-        self.speed = PLAYER_BLOCKS_PER_SECOND / renderer::FRAME_PER_SECOND as f64;
+        self.speed = speed_factor * PLAYER_BLOCKS_PER_SECOND / renderer::FRAME_PER_SECOND as f64;
+    }
+
+    /// Changes the speed of a moving player without changing the direction.
+    pub fn set_player_speed(&mut self, speed_factor: f64) {
+        if self.is_moving() {
+            self.speed =
+                speed_factor * PLAYER_BLOCKS_PER_SECOND / renderer::FRAME_PER_SECOND as f64;
+        }
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.speed > 0.
     }
 
     pub fn stop(&mut self) {
         self.speed = 0.;
     }
+
+    /// Mirrors the direction against a vertical edge, inverting the horizontal component.
+    pub fn reflect_horizontally(&mut self) {
+        self.direction = std::f64::consts::PI - self.direction;
+    }
+
+    /// Mirrors the direction against a horizontal edge, inverting the vertical component.
+    pub fn reflect_vertically(&mut self) {
+        self.direction = -self.direction;
+    }
 }
 
 #[derive(Component)]
 #[storage(VecStorage)]
 pub struct Circle {
     radius: f64,
-    pub hit_points: Vec<Position>,
 }
 
 impl Circle {
     pub fn new_player_circle() -> Circle {
         Circle {
             radius: DEFAULT_PLAYER_RADIUS,
-            hit_points: Self::build_hit_points(DEFAULT_PLAYER_RADIUS),
         }
     }
 
     pub fn new_bullet_circle() -> Circle {
         Circle {
             radius: DEFAULT_BULLET_RADIUS,
-            hit_points: Self::build_hit_points(DEFAULT_BULLET_RADIUS),
+        }
+    }
+
+    pub fn new_pickup_circle() -> Circle {
+        Circle {
+            radius: DEFAULT_PICKUP_RADIUS,
+        }
+    }
+
+    /// The circle of a mine is its trigger area.
+    pub fn new_mine_circle() -> Circle {
+        Circle {
+            radius: DEFAULT_MINE_RADIUS,
         }
     }
 
@@ -97,21 +151,6 @@ impl Circle {
     pub fn get_radius(&self) -> f64 {
         self.radius
     }
-
-    pub fn build_hit_points(radius: f64) -> Vec<Position> {
-        let mut circle_points = Vec::new();
-        let center_x = 0.0; // Assuming the circle is centered at the origin (0, 0)
-        let center_y = 0.0;
-
-        for i in 0..DEFAULT_HIT_POINTS_NUMBER {
-            let angle = (i as f64 / DEFAULT_HIT_POINTS_NUMBER as f64) * 2.0 * PI as f64;
-            let x = center_x + radius * angle.cos();
-            let y = center_y + radius * angle.sin();
-            circle_points.push(Position { x, y });
-        }
-
-        circle_points
-    }
 }
 
 #[derive(Component)]
@@ -121,10 +160,19 @@ pub struct Player {
     pub id: String,
     pub name: String,
     pub status: ReadyStatus,
+    pub team: Option<Team>,
+    /// The team was given by the game for the round, it is cleared when the round ends.
+    pub is_team_assigned: bool,
     pub aim: AimStatus,
     pub shoot: ShootStatus,
-    pub next_input: RemoteInput,
+    pub ammo: Ammo,
+    pub effects: Vec<StatusEffect>,
+    pub extra_lives: u32,
+    pub ability: Ability,
+    /// Inputs received since the last frame, in their arrival order.
+    pub inputs: Vec<RemoteInput>,
     pub is_alive: bool,
+    pub frames_until_respawn: Option<u32>,
 }
 
 #[derive(PartialEq)]
@@ -138,6 +186,39 @@ pub enum ShootStatus {
     CanShoot,
     Shooting,
     FrameLeftUntilNextShot(u32),
+    Reloading(u32),
+}
+
+pub struct Ammo {
+    pub in_magazine: u32,
+    pub reserve: u32,
+    magazine_size: u32,
+}
+
+impl Ammo {
+    pub fn new(magazine_size: u32, reserve: u32) -> Ammo {
+        Ammo {
+            in_magazine: magazine_size,
+            reserve,
+            magazine_size,
+        }
+    }
+
+    pub fn use_round(&mut self) {
+        self.in_magazine = self.in_magazine.saturating_sub(1);
+    }
+
+    pub fn can_reload(&self) -> bool {
+        self.in_magazine < self.magazine_size && self.reserve > 0
+    }
+
+    /// Moves as many rounds as possible from the reserve into the magazine.
+    pub fn reload(&mut self) {
+        let missing_rounds = self.magazine_size - self.in_magazine;
+        let loaded_rounds = missing_rounds.min(self.reserve);
+        self.in_magazine += loaded_rounds;
+        self.reserve -= loaded_rounds;
+    }
 }
 
 impl Player {
@@ -147,10 +228,17 @@ impl Player {
             id,
             name: String::new(),
             status: ReadyStatus::NotReady,
+            team: None,
+            is_team_assigned: false,
             aim: AimStatus::None,
             shoot: ShootStatus::CanShoot,
-            next_input: RemoteInput::NoInput,
+            ammo: Ammo::new(DEFAULT_MAGAZINE_SIZE, DEFAULT_RESERVE_AMMO),
+            effects: Vec::new(),
+            extra_lives: 0,
+            ability: Ability::new(AbilityKind::Mine),
+            inputs: Vec::new(),
             is_alive: true,
+            frames_until_respawn: None,
         }
     }
 
@@ -158,9 +246,116 @@ impl Player {
         self.status == ReadyStatus::Ready
     }
 
+    /// Brings a dead player back with the equipment of a new game.
+    pub fn respawn(&mut self) {
+        *self = Player {
+            name: std::mem::take(&mut self.name),
+            team: self.team,
+            is_team_assigned: self.is_team_assigned,
+            status: ReadyStatus::Ready,
+            ability: Ability::new(self.ability.kind),
+            ..Player::new(self.socket_id, std::mem::take(&mut self.id))
+        };
+    }
+
+    /// Keeps the identity of the player but resets everything related to the previous game,
+    /// only the team chosen by the player is kept.
+    pub fn reset_for_next_game(&mut self) {
+        *self = Player {
+            name: std::mem::take(&mut self.name),
+            team: self.team.filter(|_| !self.is_team_assigned),
+            ability: Ability::new(self.ability.kind),
+            ..Player::new(self.socket_id, std::mem::take(&mut self.id))
+        };
+    }
+
     pub fn update_after_shot(&mut self) {
         self.aim = AimStatus::None;
-        self.shoot = ShootStatus::FrameLeftUntilNextShot(NUMBER_OF_FRAMES_BETWEEN_SHOTS);
+        if self.ammo.in_magazine == 0 && self.ammo.can_reload() {
+            self.start_reload();
+        } else if self.has_effect(PickupKind::RapidFire) {
+            self.shoot = ShootStatus::FrameLeftUntilNextShot(
+                NUMBER_OF_FRAMES_BETWEEN_SHOTS / RAPID_FIRE_FACTOR,
+            );
+        } else {
+            self.shoot = ShootStatus::FrameLeftUntilNextShot(NUMBER_OF_FRAMES_BETWEEN_SHOTS);
+        }
+    }
+
+    pub fn start_reload(&mut self) {
+        self.shoot = ShootStatus::Reloading(NUMBER_OF_FRAMES_TO_RELOAD);
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        matches!(self.shoot, ShootStatus::Reloading(_))
+    }
+
+    pub fn apply_pickup(&mut self, kind: PickupKind) {
+        match kind {
+            PickupKind::ExtraLife => self.extra_lives += 1,
+            _ => {
+                // Picking up an active effect again restarts its timer
+                self.effects.retain(|effect| effect.kind != kind);
+                self.effects.push(StatusEffect::new(kind));
+            }
+        }
+    }
+
+    pub fn has_effect(&self, kind: PickupKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn update_effects(&mut self) {
+        for effect in self.effects.iter_mut() {
+            effect.frames_left = effect.frames_left.saturating_sub(1);
+        }
+        self.effects.retain(|effect| effect.frames_left > 0);
+    }
+
+    pub fn speed_factor(&self) -> f64 {
+        if self.ability.is_active(AbilityKind::Dash) {
+            DASH_SPEED_FACTOR
+        } else if self.has_effect(PickupKind::SpeedBoost) {
+            SPEED_BOOST_FACTOR
+        } else {
+            1.
+        }
+    }
+
+    /// Players are hidden inside their own smoke.
+    pub fn is_hidden_by_smoke(&self) -> bool {
+        self.ability.is_active(AbilityKind::Smoke)
+    }
+
+    pub fn weapon(&self) -> Weapon {
+        if self.has_effect(PickupKind::BouncingAmmo) {
+            Weapon::BouncingShell
+        } else {
+            Weapon::Shell
+        }
+    }
+
+    pub fn bullet_bounces(&self, default_bounces: u32) -> u32 {
+        if self.has_effect(PickupKind::BouncingAmmo) {
+            default_bounces + BOUNCING_AMMO_EXTRA_BOUNCES
+        } else {
+            default_bounces
+        }
+    }
+
+    /// Handles a bullet hit, the shield and extra lives are used before the player dies.
+    /// Returns true if the player was killed.
+    pub fn hit(&mut self) -> bool {
+        if self.has_effect(PickupKind::Shield) || self.ability.is_active(AbilityKind::Shield) {
+            return false;
+        }
+        if self.extra_lives > 0 {
+            self.extra_lives -= 1;
+            false
+        } else {
+            self.is_alive = false;
+            true
+        }
     }
 }
 
@@ -170,14 +365,218 @@ pub enum ReadyStatus {
     NotReady,
 }
 
+#[derive(Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+impl Team {
+    pub fn name(&self) -> &str {
+        match self {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+}
+
 #[derive(Component)]
 #[storage(VecStorage)]
 pub struct Bullet {
     pub owner_id: String,
+    pub owner_team: Option<Team>,
+    pub bounces_left: u32,
+    pub has_bounced: bool,
+    /// Interceptable bullets are destroyed when they meet another interceptable bullet.
+    pub interceptable: bool,
 }
 
 impl Bullet {
-    pub fn new(owner_id: String) -> Bullet {
-        Bullet { owner_id }
+    pub fn new(owner: &Player, bounces: u32, interceptable: bool) -> Bullet {
+        Bullet {
+            owner_id: owner.id.clone(),
+            owner_team: owner.team,
+            bounces_left: bounces,
+            has_bounced: false,
+            interceptable,
+        }
+    }
+
+    pub fn bounce(&mut self) {
+        self.bounces_left -= 1;
+        self.has_bounced = true;
+    }
+
+    /// The owner is only safe from their bullet until it ricochets.
+    pub fn can_hit(&self, player: &Player) -> bool {
+        self.owner_id != player.id || self.has_bounced
+    }
+
+    pub fn is_from_teammate(&self, player: &Player) -> bool {
+        self.owner_id != player.id && self.owner_team.is_some() && self.owner_team == player.team
+    }
+}
+
+/// Secondary action of a tank, chosen in the lobby and used with its own cooldown.
+#[derive(Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum AbilityKind {
+    Mine,
+    Dash,
+    Shield,
+    Smoke,
+}
+
+impl AbilityKind {
+    pub fn name(&self) -> &str {
+        match self {
+            AbilityKind::Mine => "Mine",
+            AbilityKind::Dash => "Dash",
+            AbilityKind::Shield => "Shield",
+            AbilityKind::Smoke => "Smoke",
+        }
+    }
+
+    fn cooldown_in_seconds(&self) -> u32 {
+        match self {
+            AbilityKind::Mine => 10,
+            AbilityKind::Dash => 5,
+            AbilityKind::Shield => 15,
+            AbilityKind::Smoke => 12,
+        }
+    }
+
+    /// Number of frames the ability stays active, a mine is dropped instantly.
+    fn duration_in_frames(&self) -> u32 {
+        match self {
+            AbilityKind::Mine => 0,
+            AbilityKind::Dash => renderer::FRAME_PER_SECOND / 3,
+            AbilityKind::Shield => 2 * renderer::FRAME_PER_SECOND,
+            AbilityKind::Smoke => 4 * renderer::FRAME_PER_SECOND,
+        }
+    }
+}
+
+pub struct Ability {
+    pub kind: AbilityKind,
+    pub frames_until_ready: u32,
+    frames_active: u32,
+    /// Set when the ability is used, until the effect is applied by the ability system.
+    pub triggered: bool,
+}
+
+impl Ability {
+    pub fn new(kind: AbilityKind) -> Ability {
+        Ability {
+            kind,
+            frames_until_ready: 0,
+            frames_active: 0,
+            triggered: false,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.frames_until_ready == 0
+    }
+
+    pub fn cooldown_progress(&self) -> f64 {
+        let cooldown_frames = self.kind.cooldown_in_seconds() * renderer::FRAME_PER_SECOND;
+        1. - self.frames_until_ready as f64 / cooldown_frames as f64
+    }
+
+    pub fn activate(&mut self) {
+        self.frames_until_ready = self.kind.cooldown_in_seconds() * renderer::FRAME_PER_SECOND;
+        self.frames_active = self.kind.duration_in_frames();
+        self.triggered = true;
+    }
+
+    pub fn is_active(&self, kind: AbilityKind) -> bool {
+        self.kind == kind && self.frames_active > 0
+    }
+
+    pub fn update(&mut self) {
+        self.frames_until_ready = self.frames_until_ready.saturating_sub(1);
+        self.frames_active = self.frames_active.saturating_sub(1);
+    }
+}
+
+/// Stationary explosive exploding when an enemy tank comes close once it is armed.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Mine {
+    pub owner_id: String,
+    pub owner_team: Option<Team>,
+    frames_until_armed: u32,
+}
+
+impl Mine {
+    pub fn new(owner: &Player) -> Mine {
+        Mine {
+            owner_id: owner.id.clone(),
+            owner_team: owner.team,
+            frames_until_armed: FRAMES_UNTIL_MINE_IS_ARMED,
+        }
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.frames_until_armed == 0
+    }
+
+    pub fn update(&mut self) {
+        self.frames_until_armed = self.frames_until_armed.saturating_sub(1);
+    }
+
+    /// The owner and their teammates never trigger the mine.
+    pub fn is_triggered_by(&self, player: &Player, friendly_fire: bool) -> bool {
+        self.owner_id != player.id
+            && (friendly_fire || self.owner_team.is_none() || self.owner_team != player.team)
+    }
+}
+
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+impl Pickup {
+    pub fn new(kind: PickupKind) -> Pickup {
+        Pickup { kind }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum PickupKind {
+    SpeedBoost,
+    Shield,
+    RapidFire,
+    ExtraLife,
+    BouncingAmmo,
+}
+
+impl PickupKind {
+    pub fn random() -> PickupKind {
+        match rand::thread_rng().gen_range(0..5) {
+            0 => PickupKind::SpeedBoost,
+            1 => PickupKind::Shield,
+            2 => PickupKind::RapidFire,
+            3 => PickupKind::ExtraLife,
+            _ => PickupKind::BouncingAmmo,
+        }
+    }
+}
+
+pub struct StatusEffect {
+    pub kind: PickupKind,
+    pub frames_left: u32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: PickupKind) -> StatusEffect {
+        StatusEffect {
+            kind,
+            frames_left: EFFECT_DURATION_IN_SECONDS * renderer::FRAME_PER_SECOND,
+        }
     }
 }
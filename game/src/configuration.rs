@@ -0,0 +1,307 @@
+use serde::Deserialize;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+
+use crate::{
+    error::{Error, ErrorKind::ConfigurationError},
+    state::Rules,
+};
+
+const DEFAULT_CONFIGURATION_FILE: &str = "configuration";
+const DEFAULT_STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+/// Prefix of the environment variables overriding the configuration file, e.g.
+/// `TANK_GAME_SERVER_URL`.
+const ENVIRONMENT_PREFIX: &str = "TANK_GAME";
+
+/// Command line options with their configuration key and description.
+const OPTIONS: [(&str, &str, &str); 9] = [
+    (
+        "--server-url",
+        "server_url",
+        "websocket URL of the signaling server",
+    ),
+    (
+        "--join-url",
+        "join_url",
+        "URL of the remote in the QR code, followed by the room id",
+    ),
+    ("--map", "map_path", "path of the map file"),
+    (
+        "--window-mode",
+        "window_mode",
+        "fullscreen, fullscreen-desktop or windowed",
+    ),
+    (
+        "--log-level",
+        "log_level",
+        "trace, debug, info, warn or error",
+    ),
+    (
+        "--signaling",
+        "signaling",
+        "server, or lan to serve the signaling from the game",
+    ),
+    (
+        "--lan-host",
+        "lan_host",
+        "address of the game on the local network, detected by default",
+    ),
+    (
+        "--lan-port",
+        "lan_port",
+        "port of the signaling on the local network",
+    ),
+    (
+        "--remote-bundle",
+        "remote_bundle_path",
+        "directory of the built remote served on the local network",
+    ),
+];
+
+#[derive(Deserialize, Clone)]
+pub struct Settings {
+    #[serde(default = "default_server_url")]
+    pub server_url: String,
+    #[serde(default = "default_join_url")]
+    pub join_url: String,
+    #[serde(default = "default_map_path")]
+    pub map_path: String,
+    #[serde(default)]
+    pub window_mode: WindowMode,
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default)]
+    pub signaling: SignalingMode,
+    /// Address of the game in the QR code in the `lan` signaling mode, detected when missing.
+    pub lan_host: Option<String>,
+    #[serde(default = "default_lan_port")]
+    pub lan_port: u16,
+    /// Directory of the built remote, served with the signaling on the local network.
+    pub remote_bundle_path: Option<String>,
+    /// STUN and TURN servers used to connect the remotes, completed by the ones sent by the
+    /// server when the room is registered.
+    #[serde(default = "default_ice_servers")]
+    pub ice_servers: Vec<IceServerSettings>,
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum WindowMode {
+    Fullscreen,
+    #[default]
+    FullscreenDesktop,
+    Windowed,
+}
+
+/// Where the remotes send their offers, to the server or to the game itself on the local
+/// network when there is no internet access.
+#[derive(Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignalingMode {
+    #[default]
+    Server,
+    Lan,
+}
+
+/// STUN or TURN server, the TURN servers need a username and a credential.
+#[derive(Deserialize, Clone, Debug)]
+pub struct IceServerSettings {
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub credential: String,
+}
+
+/// Settings given on the command line, they take precedence over the environment variables,
+/// which take precedence over the configuration file.
+#[derive(Default)]
+pub struct Arguments {
+    pub show_help: bool,
+    configuration_file: Option<String>,
+    overrides: Vec<(&'static str, String)>,
+}
+
+impl Arguments {
+    /// Parses the arguments following the program name. A lone argument without option is
+    /// still accepted as the log level.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Arguments, Error> {
+        let mut arguments = Arguments::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+            if name == "--help" || name == "-h" {
+                arguments.show_help = true;
+                continue;
+            }
+            if !name.starts_with("--") {
+                arguments.overrides.push(("log_level", name));
+                continue;
+            }
+            let key = match OPTIONS.iter().find(|(option, _, _)| *option == name) {
+                Some((_, key, _)) => Some(*key),
+                None if name == "--config" => None,
+                None => {
+                    return Err(Error::new(
+                        ConfigurationError,
+                        format!("unknown option \"{}\"", name),
+                    ))
+                }
+            };
+            let Some(value) = inline_value.or_else(|| args.next()) else {
+                return Err(Error::new(
+                    ConfigurationError,
+                    format!("missing value for option \"{}\"", name),
+                ));
+            };
+            match key {
+                Some(key) => arguments.overrides.push((key, value)),
+                None => arguments.configuration_file = Some(value),
+            }
+        }
+        Ok(arguments)
+    }
+
+    pub fn usage() -> String {
+        let mut usage = "Usage: game [OPTIONS]\n\nOptions:\n".to_owned();
+        usage.push_str(&format!(
+            "  {:<16}configuration file, \"{}\" by default\n",
+            "--config", DEFAULT_CONFIGURATION_FILE
+        ));
+        for (option, _, description) in OPTIONS {
+            usage.push_str(&format!("  {:<16}{}\n", option, description));
+        }
+        usage.push_str(&format!(
+            "  {:<16}print this help\n\nEvery setting can also be set by an environment variable, e.g. {}_SERVER_URL.",
+            "--help", ENVIRONMENT_PREFIX
+        ));
+        usage
+    }
+}
+
+/// Reads the configuration file of the game, which is optional unless given on the command
+/// line, then applies the environment variables and the command line arguments. The settings
+/// missing everywhere keep their default value.
+pub fn get_configuration(arguments: Arguments) -> Result<Settings, Error> {
+    let file = match &arguments.configuration_file {
+        Some(path) => config::File::with_name(path),
+        None => config::File::with_name(DEFAULT_CONFIGURATION_FILE).required(false),
+    };
+    let mut builder = config::Config::builder()
+        .add_source(file)
+        .add_source(config::Environment::with_prefix(ENVIRONMENT_PREFIX));
+    for (key, value) in arguments.overrides {
+        builder = builder.set_override(key, value).map_err(|err| {
+            Error::from(err, ConfigurationError)
+                .explain(format!("option for \"{}\" is invalid", key))
+        })?;
+    }
+    let settings = builder.build().map_err(|err| {
+        Error::from(err, ConfigurationError).explain("configuration file could not be read")
+    })?;
+
+    settings.try_deserialize::<Settings>().map_err(|err| {
+        Error::from(err, ConfigurationError).explain("configuration could not be deserialized")
+    })
+}
+
+fn default_server_url() -> String {
+    "ws://tank-game.flmichel.duckdns.org/ws".to_owned()
+}
+
+fn default_join_url() -> String {
+    "http://192.168.0.108:8080/?room-id=".to_owned()
+}
+
+fn default_map_path() -> String {
+    "assets/map.txt".to_owned()
+}
+
+fn default_log_level() -> String {
+    "info".to_owned()
+}
+
+fn default_lan_port() -> u16 {
+    8081
+}
+
+fn default_ice_servers() -> Vec<IceServerSettings> {
+    vec![IceServerSettings {
+        urls: vec![DEFAULT_STUN_SERVER.to_owned()],
+        username: String::new(),
+        credential: String::new(),
+    }]
+}
+
+impl From<IceServerSettings> for RTCIceServer {
+    fn from(settings: IceServerSettings) -> Self {
+        RTCIceServer {
+            urls: settings.urls,
+            username: settings.username,
+            credential: settings.credential,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Arguments, Error> {
+        Arguments::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_the_options_with_separate_or_inline_values() {
+        let arguments = parse(&["--server-url", "ws://localhost/ws", "--lan-port=9000"]).unwrap();
+
+        assert!(!arguments.show_help);
+        assert_eq!(
+            arguments.overrides,
+            vec![
+                ("server_url", "ws://localhost/ws".to_owned()),
+                ("lan_port", "9000".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_configuration_file() {
+        let arguments = parse(&["--config", "local.yaml"]).unwrap();
+
+        assert_eq!(arguments.configuration_file.as_deref(), Some("local.yaml"));
+        assert!(arguments.overrides.is_empty());
+    }
+
+    #[test]
+    fn parses_the_help() {
+        assert!(parse(&["--help"]).unwrap().show_help);
+        assert!(parse(&["--map", "map.txt", "-h"]).unwrap().show_help);
+        assert!(!parse(&[]).unwrap().show_help);
+    }
+
+    #[test]
+    fn accepts_a_lone_log_level() {
+        let arguments = parse(&["debug"]).unwrap();
+
+        assert_eq!(arguments.overrides, vec![("log_level", "debug".to_owned())]);
+    }
+
+    #[test]
+    fn rejects_the_unknown_options() {
+        assert!(parse(&["--unknown", "value"]).is_err());
+        assert!(parse(&["--server"]).is_err());
+    }
+
+    #[test]
+    fn rejects_the_options_without_value() {
+        assert!(parse(&["--map"]).is_err());
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["--log-level", "debug", "--window-mode"]).is_err());
+    }
+}
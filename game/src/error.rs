@@ -40,4 +40,6 @@ impl fmt::Display for Error {
 pub enum ErrorKind {
     ConfigurationError,
     NoSpawnAvailable,
+    InvalidMessage,
+    UnsupportedProtocolVersion,
 }
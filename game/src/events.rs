@@ -0,0 +1,70 @@
+use specs::shrev::EventChannel;
+
+use crate::{components::Position, game_modes::Kill, render::renderer, state::Block};
+
+const KILL_FEED_SIZE: usize = 5;
+const KILL_FEED_DURATION_IN_SECONDS: u32 = 5;
+
+/// Gameplay events, emitted by the systems in the `GameEvents` channel so that renderer,
+/// statistics or remote feedback can react to them without touching the gameplay systems.
+pub enum GameEvent {
+    PlayerJoined {
+        player_id: String,
+    },
+    /// Hit which didn't kill the player, thanks to a shield or an extra life.
+    PlayerHit {
+        player_id: String,
+    },
+    PlayerKilled(Kill),
+    BulletFired {
+        owner_id: String,
+        position: Position,
+    },
+    BulletHitWall {
+        block: Block,
+        position: Position,
+    },
+    RoundEnded {
+        winner: String,
+    },
+}
+
+pub type GameEvents = EventChannel<GameEvent>;
+
+/// Last kills of the round displayed by the renderer, built from the `PlayerKilled` events.
+#[derive(Default)]
+pub struct KillFeed {
+    entries: Vec<KillFeedEntry>,
+}
+
+pub struct KillFeedEntry {
+    pub text: String,
+    frames_left: u32,
+}
+
+impl KillFeed {
+    pub fn push(&mut self, text: String) {
+        if self.entries.len() == KILL_FEED_SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push(KillFeedEntry {
+            text,
+            frames_left: KILL_FEED_DURATION_IN_SECONDS * renderer::FRAME_PER_SECOND,
+        });
+    }
+
+    pub fn update(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.frames_left = entry.frames_left.saturating_sub(1);
+        }
+        self.entries.retain(|entry| entry.frames_left > 0);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> &[KillFeedEntry] {
+        &self.entries
+    }
+}
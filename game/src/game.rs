@@ -1,11 +1,21 @@
 use specs::{Component, VecStorage};
 use specs_derive::Component;
 
-use crate::remotes::PlayerInput;
+use crate::{
+    remotes::{ConnectionStats, PlayerInput},
+    server_communicator::ServerStatus,
+};
 
 pub enum MessageToGame {
     PlayerInput(PlayerInput),
     RoomId(RoomId),
+    ConnectionStats {
+        socket_id: u32,
+        stats: ConnectionStats,
+    },
+    /// The peer connection of the remote with this socket id was closed.
+    ConnectionClosed(u32),
+    ServerStatus(ServerStatus),
 }
 
 #[derive(Component)]
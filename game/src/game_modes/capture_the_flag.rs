@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use super::{
+    seconds_to_frames, sort_scores, team_side, GameMode, GameModeKind, Objective, ObjectiveKind,
+    PlayerView, Score, Side, RESPAWN_DELAY_IN_SECONDS,
+};
+use crate::{
+    components::{Position, Team},
+    state::Map,
+};
+
+const ROUND_DURATION_IN_SECONDS: u32 = 300;
+const CAPTURES_TO_WIN: i32 = 3;
+const FLAG_REACH: f64 = 0.4;
+
+/// Each team has a flag at its base, bringing the enemy flag home while the own flag is
+/// there scores a capture. The round also ends when the time is up, e.g. on the maps without
+/// team bases.
+pub struct CaptureTheFlag {
+    frames_left: u32,
+    flags: Vec<Flag>,
+    captures: HashMap<Team, i32>,
+}
+
+impl Default for CaptureTheFlag {
+    fn default() -> Self {
+        CaptureTheFlag {
+            frames_left: seconds_to_frames(ROUND_DURATION_IN_SECONDS),
+            flags: Vec::new(),
+            captures: HashMap::new(),
+        }
+    }
+}
+
+struct Flag {
+    team: Team,
+    home: Position,
+    position: Position,
+    carrier_id: Option<String>,
+}
+
+impl Flag {
+    fn is_at_home(&self) -> bool {
+        self.carrier_id.is_none() && distance(&self.position, &self.home) < f64::EPSILON
+    }
+
+    fn return_home(&mut self) {
+        self.position = self.home.clone();
+        self.carrier_id = None;
+    }
+}
+
+impl GameMode for CaptureTheFlag {
+    fn kind(&self) -> GameModeKind {
+        GameModeKind::CaptureTheFlag
+    }
+
+    fn uses_teams(&self) -> bool {
+        true
+    }
+
+    fn on_round_start(&mut self, _players: &[PlayerView], map: &Map) {
+        *self = CaptureTheFlag::default();
+        self.flags = [Team::Red, Team::Blue]
+            .iter()
+            .filter_map(|team| {
+                map.get_team_base(*team).map(|block| Flag {
+                    team: *team,
+                    home: block.center(),
+                    position: block.center(),
+                    carrier_id: None,
+                })
+            })
+            .collect();
+    }
+
+    fn on_tick(&mut self, players: &[PlayerView], _map: &Map) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+        let flags_at_home: Vec<(Team, Position)> = self
+            .flags
+            .iter()
+            .filter(|flag| flag.is_at_home())
+            .map(|flag| (flag.team, flag.home.clone()))
+            .collect();
+
+        for flag in self.flags.iter_mut() {
+            match &flag.carrier_id {
+                Some(carrier_id) => {
+                    let carrier = players
+                        .iter()
+                        .find(|player| &player.id == carrier_id && player.is_alive);
+                    match carrier.and_then(|carrier| carrier.position.clone().zip(carrier.team)) {
+                        Some((carrier_position, carrier_team)) => {
+                            flag.position = carrier_position;
+                            let is_captured = flags_at_home.iter().any(|(team, home)| {
+                                *team == carrier_team && distance(&flag.position, home) < FLAG_REACH
+                            });
+                            if is_captured {
+                                *self.captures.entry(carrier_team).or_insert(0) += 1;
+                                flag.return_home();
+                            }
+                        }
+                        // The flag is dropped where its carrier died
+                        None => flag.carrier_id = None,
+                    }
+                }
+                None => {
+                    let toucher = players.iter().find(|player| {
+                        player.is_alive
+                            && player.position.as_ref().is_some_and(|position| {
+                                distance(position, &flag.position) < FLAG_REACH
+                            })
+                    });
+                    if let Some(toucher) = toucher {
+                        if toucher.team != Some(flag.team) {
+                            flag.carrier_id = Some(toucher.id.clone());
+                        } else if !flag.is_at_home() {
+                            flag.return_home();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn respawn_delay(&self) -> Option<u32> {
+        Some(seconds_to_frames(RESPAWN_DELAY_IN_SECONDS))
+    }
+
+    fn is_round_over(&self, _players: &[PlayerView]) -> bool {
+        self.frames_left == 0
+            || self
+                .captures
+                .values()
+                .any(|captures| *captures >= CAPTURES_TO_WIN)
+    }
+
+    fn scores(&self, _players: &[PlayerView]) -> Vec<Score> {
+        sort_scores(
+            [Team::Red, Team::Blue]
+                .iter()
+                .map(|team| Score {
+                    side: Side::Team(*team),
+                    name: team_side(*team),
+                    points: *self.captures.get(team).unwrap_or(&0),
+                })
+                .collect(),
+        )
+    }
+
+    fn objectives(&self) -> Vec<Objective> {
+        self.flags
+            .iter()
+            .map(|flag| Objective {
+                position: flag.position.clone(),
+                kind: ObjectiveKind::Flag(flag.team),
+            })
+            .collect()
+    }
+}
+
+fn distance(first: &Position, second: &Position) -> f64 {
+    ((first.x - second.x).powi(2) + (first.y - second.y).powi(2)).sqrt()
+}
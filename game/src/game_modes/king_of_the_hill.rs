@@ -0,0 +1,90 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    seconds_to_frames, sort_scores, GameMode, GameModeKind, Objective, ObjectiveKind, PlayerView,
+    Score, Side, RESPAWN_DELAY_IN_SECONDS,
+};
+use crate::{render::renderer, state::Block, state::Map};
+
+const SECONDS_TO_WIN: u32 = 60;
+
+/// The hill is controlled while a single player or team stands on it, the first to control it
+/// long enough wins.
+#[derive(Default)]
+pub struct KingOfTheHill {
+    hill: Vec<Block>,
+    control_frames: HashMap<Side, u32>,
+}
+
+impl GameMode for KingOfTheHill {
+    fn kind(&self) -> GameModeKind {
+        GameModeKind::KingOfTheHill
+    }
+
+    fn on_round_start(&mut self, _players: &[PlayerView], map: &Map) {
+        self.control_frames.clear();
+        self.hill = map.get_hill_blocks().to_vec();
+        // Maps without a marked hill get a random one
+        if self.hill.is_empty() {
+            self.hill.extend(map.get_pickup_block(&[]));
+        }
+    }
+
+    fn on_tick(&mut self, players: &[PlayerView], _map: &Map) {
+        let sides_on_hill: HashSet<Side> = players
+            .iter()
+            .filter(|player| player.is_alive)
+            .filter(|player| {
+                player
+                    .position
+                    .as_ref()
+                    .is_some_and(|position| self.hill.contains(&position.block()))
+            })
+            .map(PlayerView::side)
+            .collect();
+
+        if sides_on_hill.len() == 1 {
+            for side in sides_on_hill {
+                *self.control_frames.entry(side).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn respawn_delay(&self) -> Option<u32> {
+        Some(seconds_to_frames(RESPAWN_DELAY_IN_SECONDS))
+    }
+
+    fn is_round_over(&self, _players: &[PlayerView]) -> bool {
+        self.control_frames
+            .values()
+            .any(|frames| *frames >= seconds_to_frames(SECONDS_TO_WIN))
+    }
+
+    fn scores(&self, players: &[PlayerView]) -> Vec<Score> {
+        let sides: HashMap<Side, String> = players
+            .iter()
+            .map(|player| (player.side(), player.side_name()))
+            .collect();
+        sort_scores(
+            sides
+                .into_iter()
+                .map(|(side, name)| Score {
+                    points: (*self.control_frames.get(&side).unwrap_or(&0)
+                        / renderer::FRAME_PER_SECOND) as i32,
+                    side,
+                    name,
+                })
+                .collect(),
+        )
+    }
+
+    fn objectives(&self) -> Vec<Objective> {
+        self.hill
+            .iter()
+            .map(|block| Objective {
+                position: block.center(),
+                kind: ObjectiveKind::Hill,
+            })
+            .collect()
+    }
+}
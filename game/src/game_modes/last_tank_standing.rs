@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use super::{sort_scores, GameMode, GameModeKind, PlayerView, Score, Side};
+
+/// Dead players wait for the next round, the last player or team alive wins.
+pub struct LastTankStanding;
+
+impl GameMode for LastTankStanding {
+    fn kind(&self) -> GameModeKind {
+        GameModeKind::LastTankStanding
+    }
+
+    fn is_round_over(&self, players: &[PlayerView]) -> bool {
+        let sides: HashSet<Side> = players.iter().map(PlayerView::side).collect();
+        let alive_sides: HashSet<Side> = players
+            .iter()
+            .filter(|player| player.is_alive)
+            .map(PlayerView::side)
+            .collect();
+
+        // A player alone in the game plays until they die
+        alive_sides.is_empty() || (alive_sides.len() == 1 && sides.len() > 1)
+    }
+
+    fn scores(&self, players: &[PlayerView]) -> Vec<Score> {
+        let mut scores: Vec<Score> = Vec::new();
+        for player in players {
+            let points = if player.is_alive { 1 } else { 0 };
+            match scores.iter_mut().find(|score| score.side == player.side()) {
+                Some(score) => score.points += points,
+                None => scores.push(Score {
+                    side: player.side(),
+                    name: player.side_name(),
+                    points,
+                }),
+            }
+        }
+        sort_scores(scores)
+    }
+}
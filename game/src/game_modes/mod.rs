@@ -0,0 +1,178 @@
+mod capture_the_flag;
+mod king_of_the_hill;
+mod last_tank_standing;
+mod team_deathmatch;
+mod timed_deathmatch;
+
+pub use capture_the_flag::CaptureTheFlag;
+pub use king_of_the_hill::KingOfTheHill;
+pub use last_tank_standing::LastTankStanding;
+pub use team_deathmatch::TeamDeathmatch;
+pub use timed_deathmatch::TimedDeathmatch;
+
+use serde::Deserialize;
+use std::cmp::Reverse;
+
+use crate::{
+    components::{Player, Position, Team},
+    render::renderer,
+    state::Map,
+};
+
+const RESPAWN_DELAY_IN_SECONDS: u32 = 3;
+
+/// Rules deciding how a round is played and won.
+pub trait GameMode: Send + Sync {
+    fn kind(&self) -> GameModeKind;
+
+    /// Players are put in teams before the round starts when true.
+    fn uses_teams(&self) -> bool {
+        false
+    }
+
+    /// Called once the players are spawned.
+    fn on_round_start(&mut self, _players: &[PlayerView], _map: &Map) {}
+
+    /// Called when a player loses their last life.
+    fn on_kill(&mut self, _kill: &Kill) {}
+
+    /// Called every frame while the round is in progress.
+    fn on_tick(&mut self, _players: &[PlayerView], _map: &Map) {}
+
+    /// Number of frames before a dead player comes back, `None` if they wait for the next round.
+    fn respawn_delay(&self) -> Option<u32> {
+        None
+    }
+
+    fn is_round_over(&self, players: &[PlayerView]) -> bool;
+
+    /// Scores sorted from the best to the worst.
+    fn scores(&self, players: &[PlayerView]) -> Vec<Score>;
+
+    /// The best score wins, there is no winner in case of a tie.
+    fn winner(&self, players: &[PlayerView]) -> Option<String> {
+        let scores = self.scores(players);
+        match (scores.first(), scores.get(1)) {
+            (Some(first), Some(second)) if first.points == second.points => None,
+            (Some(first), _) => Some(first.name.clone()),
+            (None, _) => None,
+        }
+    }
+
+    /// Objects of the game mode to display on the map.
+    fn objectives(&self) -> Vec<Objective> {
+        Vec::new()
+    }
+}
+
+#[derive(Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameModeKind {
+    LastTankStanding,
+    TimedDeathmatch,
+    TeamDeathmatch,
+    CaptureTheFlag,
+    KingOfTheHill,
+}
+
+impl GameModeKind {
+    pub fn create(&self) -> Box<dyn GameMode> {
+        match self {
+            GameModeKind::LastTankStanding => Box::new(LastTankStanding),
+            GameModeKind::TimedDeathmatch => Box::<TimedDeathmatch>::default(),
+            GameModeKind::TeamDeathmatch => Box::<TeamDeathmatch>::default(),
+            GameModeKind::CaptureTheFlag => Box::<CaptureTheFlag>::default(),
+            GameModeKind::KingOfTheHill => Box::<KingOfTheHill>::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            GameModeKind::LastTankStanding => "Last tank standing",
+            GameModeKind::TimedDeathmatch => "Timed deathmatch",
+            GameModeKind::TeamDeathmatch => "Team deathmatch",
+            GameModeKind::CaptureTheFlag => "Capture the flag",
+            GameModeKind::KingOfTheHill => "King of the hill",
+        }
+    }
+}
+
+/// Copy of the player data needed by the game modes.
+pub struct PlayerView {
+    pub id: String,
+    pub name: String,
+    pub team: Option<Team>,
+    pub is_alive: bool,
+    pub position: Option<Position>,
+}
+
+impl PlayerView {
+    pub fn new(player: &Player, position: Option<&Position>) -> PlayerView {
+        PlayerView {
+            id: player.id.clone(),
+            name: player.name.clone(),
+            team: player.team,
+            is_alive: player.is_alive,
+            position: position.cloned(),
+        }
+    }
+
+    pub fn side(&self) -> Side {
+        match self.team {
+            Some(team) => Side::Team(team),
+            None => Side::Player(self.id.clone()),
+        }
+    }
+
+    /// Name of the team of the player, or of the player if they play alone.
+    pub fn side_name(&self) -> String {
+        match self.team {
+            Some(team) => team_side(team),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Team of the player, or the player if they play alone. The players alone are identified by
+/// their id since several players can have the same name.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub enum Side {
+    Team(Team),
+    Player(String),
+}
+
+pub struct Kill {
+    pub killer_id: String,
+    pub killer_team: Option<Team>,
+    pub victim: PlayerView,
+}
+
+pub struct Score {
+    pub side: Side,
+    /// Displayed name of the side.
+    pub name: String,
+    pub points: i32,
+}
+
+pub struct Objective {
+    pub position: Position,
+    pub kind: ObjectiveKind,
+}
+
+pub enum ObjectiveKind {
+    Flag(Team),
+    Hill,
+}
+
+fn team_side(team: Team) -> String {
+    format!("{} team", team.name())
+}
+
+fn sort_scores(mut scores: Vec<Score>) -> Vec<Score> {
+    scores.sort_by_key(|score| Reverse(score.points));
+    scores
+}
+
+fn seconds_to_frames(seconds: u32) -> u32 {
+    seconds * renderer::FRAME_PER_SECOND
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use super::{
+    seconds_to_frames, sort_scores, team_side, GameMode, GameModeKind, Kill, PlayerView, Score,
+    Side, RESPAWN_DELAY_IN_SECONDS,
+};
+use crate::{components::Team, state::Map};
+
+const ROUND_DURATION_IN_SECONDS: u32 = 300;
+const KILLS_TO_WIN: i32 = 20;
+
+/// Players respawn and the first team reaching the kill target, or leading at the end, wins.
+pub struct TeamDeathmatch {
+    frames_left: u32,
+    kills: HashMap<Team, i32>,
+}
+
+impl Default for TeamDeathmatch {
+    fn default() -> Self {
+        TeamDeathmatch {
+            frames_left: seconds_to_frames(ROUND_DURATION_IN_SECONDS),
+            kills: HashMap::new(),
+        }
+    }
+}
+
+impl GameMode for TeamDeathmatch {
+    fn kind(&self) -> GameModeKind {
+        GameModeKind::TeamDeathmatch
+    }
+
+    fn uses_teams(&self) -> bool {
+        true
+    }
+
+    fn on_round_start(&mut self, _players: &[PlayerView], _map: &Map) {
+        *self = TeamDeathmatch::default();
+    }
+
+    fn on_kill(&mut self, kill: &Kill) {
+        // Team kills and suicides are not counted
+        if let Some(killer_team) = kill.killer_team {
+            if Some(killer_team) != kill.victim.team {
+                *self.kills.entry(killer_team).or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn on_tick(&mut self, _players: &[PlayerView], _map: &Map) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+
+    fn respawn_delay(&self) -> Option<u32> {
+        Some(seconds_to_frames(RESPAWN_DELAY_IN_SECONDS))
+    }
+
+    fn is_round_over(&self, _players: &[PlayerView]) -> bool {
+        self.frames_left == 0 || self.kills.values().any(|kills| *kills >= KILLS_TO_WIN)
+    }
+
+    fn scores(&self, _players: &[PlayerView]) -> Vec<Score> {
+        sort_scores(
+            [Team::Red, Team::Blue]
+                .iter()
+                .map(|team| Score {
+                    side: Side::Team(*team),
+                    name: team_side(*team),
+                    points: *self.kills.get(team).unwrap_or(&0),
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use super::{
+    seconds_to_frames, sort_scores, GameMode, GameModeKind, Kill, PlayerView, Score,
+    RESPAWN_DELAY_IN_SECONDS,
+};
+use crate::state::Map;
+
+const ROUND_DURATION_IN_SECONDS: u32 = 180;
+
+/// Players respawn and the one with the most kills when the time is up wins.
+pub struct TimedDeathmatch {
+    frames_left: u32,
+    kills: HashMap<String, i32>,
+}
+
+impl Default for TimedDeathmatch {
+    fn default() -> Self {
+        TimedDeathmatch {
+            frames_left: seconds_to_frames(ROUND_DURATION_IN_SECONDS),
+            kills: HashMap::new(),
+        }
+    }
+}
+
+impl GameMode for TimedDeathmatch {
+    fn kind(&self) -> GameModeKind {
+        GameModeKind::TimedDeathmatch
+    }
+
+    fn on_round_start(&mut self, _players: &[PlayerView], _map: &Map) {
+        *self = TimedDeathmatch::default();
+    }
+
+    fn on_kill(&mut self, kill: &Kill) {
+        let kills = self.kills.entry(kill.killer_id.clone()).or_insert(0);
+        if kill.killer_id == kill.victim.id {
+            *kills -= 1;
+        } else {
+            *kills += 1;
+        }
+    }
+
+    fn on_tick(&mut self, _players: &[PlayerView], _map: &Map) {
+        self.frames_left = self.frames_left.saturating_sub(1);
+    }
+
+    fn respawn_delay(&self) -> Option<u32> {
+        Some(seconds_to_frames(RESPAWN_DELAY_IN_SECONDS))
+    }
+
+    fn is_round_over(&self, _players: &[PlayerView]) -> bool {
+        self.frames_left == 0
+    }
+
+    /// Scores by side, the kills of teammates are added up.
+    fn scores(&self, players: &[PlayerView]) -> Vec<Score> {
+        let mut scores: Vec<Score> = Vec::new();
+        for player in players {
+            let points = *self.kills.get(&player.id).unwrap_or(&0);
+            match scores.iter_mut().find(|score| score.side == player.side()) {
+                Some(score) => score.points += points,
+                None => scores.push(Score {
+                    side: player.side(),
+                    name: player.side_name(),
+                    points,
+                }),
+            }
+        }
+        sort_scores(scores)
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::{Component, Path as FilePath, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::{
+    spawn,
+    sync::{mpsc, Mutex},
+    time::timeout,
+};
+use tower_http::cors::CorsLayer;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    game::{MessageToGame, RoomId},
+    server_communicator::{
+        IceCandidateMessage, MessageToServer, SdpMessage, ServerStatus, SignalingMessage,
+    },
+};
+
+/// Id of the only room, the one of the game serving the signaling.
+const LAN_ROOM_ID: &str = "lan";
+/// Maximum time a poll of the ice candidates waits for a new candidate of the game.
+const CANDIDATES_POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the signaling endpoints of the server, and optionally the remote, on the local
+/// network so the remotes can join without the central server. It talks with the players
+/// connector like the `ServerCommunicator`.
+pub struct LanSignaling {
+    sender_to_game: UnboundedSender<MessageToGame>,
+    sender_to_player_connector: UnboundedSender<SignalingMessage>,
+    host: String,
+    port: u16,
+    remote_bundle_path: Option<PathBuf>,
+}
+
+/// Replies of the players connector by signaling session id.
+type ReplyRoutes = Arc<std::sync::Mutex<HashMap<u32, mpsc::UnboundedSender<MessageToServer>>>>;
+/// Signaling sessions answered by the players connector, polled by their remote.
+type Sessions = Arc<Mutex<HashMap<u32, Arc<Mutex<LanSession>>>>>;
+
+#[derive(Clone)]
+struct LanContext {
+    sender_to_player_connector: UnboundedSender<SignalingMessage>,
+    reply_routes: ReplyRoutes,
+    sessions: Sessions,
+    remote_bundle_path: Option<PathBuf>,
+}
+
+struct LanSession {
+    /// Candidates of the game gathered before its answer.
+    early_candidates: Vec<String>,
+    /// The game gathered all its candidates before its answer.
+    gathering_done: bool,
+    replies: mpsc::UnboundedReceiver<MessageToServer>,
+}
+
+/// Same bodies as the responses of the server, the remote doesn't know who answers it.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct SdpAnswer {
+    answer: String,
+    session_id: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct IceCandidates {
+    candidates: Vec<String>,
+    done: bool,
+}
+
+impl LanSignaling {
+    /// Without `host`, the address of the computer on the local network is detected.
+    pub fn new(
+        sender_to_game: UnboundedSender<MessageToGame>,
+        sender_to_player_connector: UnboundedSender<SignalingMessage>,
+        host: Option<String>,
+        port: u16,
+        remote_bundle_path: Option<String>,
+    ) -> Self {
+        let host = host.unwrap_or_else(|| match detect_lan_address() {
+            Some(address) => address.to_string(),
+            None => {
+                warn!("Failed to detect the address on the local network, set the lan host.");
+                Ipv4Addr::LOCALHOST.to_string()
+            }
+        });
+        Self {
+            sender_to_game,
+            sender_to_player_connector,
+            host,
+            port,
+            remote_bundle_path: remote_bundle_path.map(PathBuf::from),
+        }
+    }
+
+    /// URL of the remote served by the game, using the game as server. Like the join URL of
+    /// the server, it is followed by the room id.
+    pub fn join_url(&self) -> String {
+        let origin = format!("http://{}:{}", self.host, self.port);
+        format!("{origin}/?api-base-url={origin}/api&room-id=")
+    }
+
+    pub async fn start(self, receiver: UnboundedReceiver<MessageToServer>) {
+        let context = LanContext {
+            sender_to_player_connector: self.sender_to_player_connector,
+            reply_routes: ReplyRoutes::default(),
+            sessions: Sessions::default(),
+            remote_bundle_path: self.remote_bundle_path,
+        };
+        spawn(route_replies(receiver, Arc::clone(&context.reply_routes)));
+
+        let app = Router::new()
+            .route("/api/game/:id", post(post_sdp_session))
+            .route(
+                "/api/game/:id/:session_id/candidates",
+                get(get_ice_candidates).post(post_ice_candidate),
+            )
+            .fallback(serve_remote_bundle)
+            .layer(CorsLayer::permissive())
+            .with_state(context);
+
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), self.port);
+        let server = match axum::Server::try_bind(&address) {
+            Ok(server) => server,
+            Err(err) => {
+                error!("Failed to serve the signaling on {}: {}.", address, err);
+                return;
+            }
+        };
+        info!(
+            "Serving the signaling on the local network at {}:{}.",
+            self.host, self.port
+        );
+        for message in [
+            MessageToGame::ServerStatus(ServerStatus::LocalNetwork),
+            MessageToGame::RoomId(RoomId(LAN_ROOM_ID.to_owned())),
+        ] {
+            if let Err(err) = self.sender_to_game.unbounded_send(message) {
+                debug!("failed to send message {}", err.to_string());
+            }
+        }
+        if let Err(err) = server.serve(app.into_make_service()).await {
+            error!("The signaling on the local network stopped: {}.", err);
+        }
+    }
+}
+
+/// Address of the computer on the route to a public address, no packet is sent.
+fn detect_lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(8, 8, 8, 8), 80)).ok()?;
+    socket.local_addr().ok().map(|address| address.ip())
+}
+
+/// Forwards the answers and the candidates of the players connector to their session.
+async fn route_replies(
+    mut receiver: UnboundedReceiver<MessageToServer>,
+    reply_routes: ReplyRoutes,
+) {
+    while let Some(message) = receiver.next().await {
+        let id = match &message {
+            MessageToServer::SdpAnswer(answer) => answer.id,
+            MessageToServer::SdpError(error) => error.id,
+            MessageToServer::IceCandidate(candidate) => candidate.id,
+        };
+        if let Some(route) = reply_routes.lock().unwrap().get(&id) {
+            if route.send(message).is_err() {
+                debug!("the remote of the signaling session {} is gone", id);
+            }
+        }
+    }
+}
+
+async fn post_sdp_session(
+    State(context): State<LanContext>,
+    Path(id): Path<String>,
+    body: String,
+) -> impl IntoResponse {
+    if id != LAN_ROOM_ID {
+        return (StatusCode::NOT_FOUND, "room not found".to_owned());
+    }
+    debug!("received spd offer on the local network");
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let session_id = rand::random::<u32>();
+    context.reply_routes.lock().unwrap().insert(session_id, tx);
+    let offer = SignalingMessage::Offer(SdpMessage {
+        id: session_id,
+        data: body.replace('"', ""),
+    });
+    if context
+        .sender_to_player_connector
+        .unbounded_send(offer)
+        .is_err()
+    {
+        context.reply_routes.lock().unwrap().remove(&session_id);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "problem with channel".to_owned(),
+        );
+    }
+
+    let mut early_candidates = Vec::new();
+    let mut gathering_done = false;
+    loop {
+        match rx.recv().await {
+            Some(MessageToServer::IceCandidate(IceCandidateMessage {
+                candidate: Some(candidate),
+                ..
+            })) => early_candidates.push(candidate),
+            Some(MessageToServer::IceCandidate(IceCandidateMessage {
+                candidate: None, ..
+            })) => gathering_done = true,
+            Some(MessageToServer::SdpAnswer(answer)) => {
+                let session = LanSession {
+                    early_candidates,
+                    gathering_done,
+                    replies: rx,
+                };
+                context
+                    .sessions
+                    .lock()
+                    .await
+                    .insert(session_id, Arc::new(Mutex::new(session)));
+                let answer = SdpAnswer {
+                    answer: answer.data,
+                    session_id,
+                };
+                return (StatusCode::OK, serde_json::to_string(&answer).unwrap());
+            }
+            Some(MessageToServer::SdpError(error)) => {
+                context.reply_routes.lock().unwrap().remove(&session_id);
+                return (StatusCode::BAD_REQUEST, error.error);
+            }
+            None => {
+                context.reply_routes.lock().unwrap().remove(&session_id);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "problem with channel".to_owned(),
+                );
+            }
+        }
+    }
+}
+
+async fn post_ice_candidate(
+    State(context): State<LanContext>,
+    Path((_, session_id)): Path<(String, u32)>,
+    body: String,
+) -> impl IntoResponse {
+    let candidate = SignalingMessage::IceCandidate(IceCandidateMessage {
+        id: session_id,
+        candidate: Some(body),
+    });
+    match context.sender_to_player_connector.unbounded_send(candidate) {
+        Ok(()) => (StatusCode::OK, "{}".to_owned()),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "problem with channel".to_owned(),
+        ),
+    }
+}
+
+/// Returns the candidates of the game gathered since the last poll, waiting for the next one
+/// when there is none yet.
+async fn get_ice_candidates(
+    State(context): State<LanContext>,
+    Path((_, session_id)): Path<(String, u32)>,
+) -> impl IntoResponse {
+    let Some(session) = context.sessions.lock().await.get(&session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "session not found".to_owned());
+    };
+    let mut session = session.lock().await;
+
+    let mut candidates = IceCandidates {
+        candidates: std::mem::take(&mut session.early_candidates),
+        done: session.gathering_done,
+    };
+    let mut next_reply = if !candidates.candidates.is_empty() || candidates.done {
+        session.replies.try_recv().ok()
+    } else {
+        match timeout(CANDIDATES_POLL_TIMEOUT, session.replies.recv()).await {
+            Ok(Some(reply)) => Some(reply),
+            // The players connector is gone
+            Ok(None) => {
+                candidates.done = true;
+                None
+            }
+            Err(_) => None,
+        }
+    };
+    while let Some(reply) = next_reply {
+        if let MessageToServer::IceCandidate(candidate) = reply {
+            match candidate.candidate {
+                Some(candidate) => candidates.candidates.push(candidate),
+                None => candidates.done = true,
+            }
+        }
+        next_reply = session.replies.try_recv().ok();
+    }
+
+    if candidates.done {
+        context.sessions.lock().await.remove(&session_id);
+        context.reply_routes.lock().unwrap().remove(&session_id);
+    }
+    (StatusCode::OK, serde_json::to_string(&candidates).unwrap())
+}
+
+/// Serves the files of the built remote, when its path is configured.
+async fn serve_remote_bundle(State(context): State<LanContext>, uri: Uri) -> Response {
+    let Some(remote_bundle_path) = &context.remote_bundle_path else {
+        return (StatusCode::NOT_FOUND, "no remote served").into_response();
+    };
+    let path = match uri.path().trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+    let path = FilePath::new(path);
+    // Only the files inside the bundle are served
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return (StatusCode::NOT_FOUND, "file not found").into_response();
+    }
+    let path = remote_bundle_path.join(path);
+    match tokio::fs::read(&path).await {
+        Ok(content) => ([(header::CONTENT_TYPE, get_content_type(&path))], content).into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, "file not found").into_response(),
+    }
+}
+
+fn get_content_type(path: &FilePath) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
@@ -1,6 +1,11 @@
 pub mod components;
+pub mod configuration;
 pub mod error;
+pub mod events;
 pub mod game;
+pub mod game_modes;
+pub mod lan_signaling;
+pub mod local_players;
 pub mod players_connector;
 pub mod remotes;
 pub mod render;
@@ -0,0 +1,381 @@
+use std::collections::{HashMap, VecDeque};
+
+use sdl2::{
+    controller::{Axis, Button, GameController},
+    event::Event,
+    keyboard::Keycode,
+    GameControllerSubsystem,
+};
+use tracing::{debug, warn};
+
+use crate::remotes::{ConfigurationInput, GameInput, PlayerInput, RemoteInput};
+
+/// Socket ids of the local players, far above the ones given to the remotes.
+const KEYBOARD_SOCKET_ID_BASE: u32 = 1 << 31;
+const CONTROLLER_SOCKET_ID_BASE: u32 = KEYBOARD_SOCKET_ID_BASE + 16;
+/// Part of the stick range ignored around its center.
+const STICK_DEAD_ZONE: f64 = 0.3;
+/// Rotation of the aim in radians per frame while an aim key is held.
+const AIM_ROTATION_SPEED: f64 = 0.08;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LocalAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    AimLeft,
+    AimRight,
+    Shoot,
+    Reload,
+    UseAbility,
+    ToggleReady,
+}
+
+/// Two players can share the keyboard, one on the left side and one on the right side.
+const KEYBOARD_LAYOUTS: [&[(Keycode, LocalAction)]; 2] = [
+    &[
+        (Keycode::W, LocalAction::Up),
+        (Keycode::S, LocalAction::Down),
+        (Keycode::A, LocalAction::Left),
+        (Keycode::D, LocalAction::Right),
+        (Keycode::Q, LocalAction::AimLeft),
+        (Keycode::E, LocalAction::AimRight),
+        (Keycode::Space, LocalAction::Shoot),
+        (Keycode::R, LocalAction::Reload),
+        (Keycode::LShift, LocalAction::UseAbility),
+        (Keycode::Num1, LocalAction::ToggleReady),
+    ],
+    &[
+        (Keycode::Up, LocalAction::Up),
+        (Keycode::Down, LocalAction::Down),
+        (Keycode::Left, LocalAction::Left),
+        (Keycode::Right, LocalAction::Right),
+        (Keycode::Comma, LocalAction::AimLeft),
+        (Keycode::Period, LocalAction::AimRight),
+        (Keycode::RCtrl, LocalAction::Shoot),
+        (Keycode::Slash, LocalAction::Reload),
+        (Keycode::RShift, LocalAction::UseAbility),
+        (Keycode::Return, LocalAction::ToggleReady),
+    ],
+];
+
+/// Players sitting at the game computer with a keyboard layout or a game controller. Their
+/// inputs go through the same pipeline as the ones of the remotes, with synthetic socket ids.
+pub struct LocalPlayers {
+    controller_subsystem: Option<GameControllerSubsystem>,
+    /// Opened controllers by instance id, they are closed when dropped.
+    controllers: HashMap<u32, GameController>,
+    players: HashMap<u32, LocalPlayer>,
+}
+
+impl LocalPlayers {
+    /// Without controller subsystem, only the keyboard layouts are available.
+    pub fn new(controller_subsystem: Option<GameControllerSubsystem>) -> LocalPlayers {
+        LocalPlayers {
+            controller_subsystem,
+            controllers: HashMap::new(),
+            players: HashMap::new(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => self.handle_key(*keycode, true),
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => self.handle_key(*keycode, false),
+            Event::ControllerDeviceAdded { which, .. } => self.open_controller(*which),
+            Event::ControllerDeviceRemoved { which, .. } => {
+                debug!("Game controller {which} removed.");
+                self.controllers.remove(which);
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let value = *value as f64 / i16::MAX as f64;
+                let player = self.controller_player(*which);
+                match axis {
+                    Axis::LeftX => player.set_stick_movement(Some(value), None),
+                    Axis::LeftY => player.set_stick_movement(None, Some(value)),
+                    Axis::RightX => player.set_stick_aim(Some(value), None),
+                    Axis::RightY => player.set_stick_aim(None, Some(value)),
+                    Axis::TriggerLeft | Axis::TriggerRight => {}
+                }
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                let action = match button {
+                    Button::A | Button::RightShoulder => LocalAction::Shoot,
+                    Button::X => LocalAction::Reload,
+                    Button::B | Button::LeftShoulder => LocalAction::UseAbility,
+                    Button::Start => LocalAction::ToggleReady,
+                    _ => return,
+                };
+                self.controller_player(*which).handle_action(action, true);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns at most one input per local player, the game reads one input per player and frame.
+    pub fn next_inputs(&mut self) -> Vec<PlayerInput> {
+        self.players
+            .values_mut()
+            .filter_map(|player| {
+                player.rotate_aim();
+                player
+                    .pending_inputs
+                    .pop_front()
+                    .map(|remote_input| PlayerInput {
+                        socket_id: player.socket_id,
+                        remote_input,
+                    })
+            })
+            .collect()
+    }
+
+    fn handle_key(&mut self, keycode: Keycode, is_pressed: bool) {
+        for (index, layout) in KEYBOARD_LAYOUTS.iter().enumerate() {
+            if let Some((_, action)) = layout.iter().find(|(key, _)| *key == keycode) {
+                let socket_id = KEYBOARD_SOCKET_ID_BASE + index as u32;
+                self.players
+                    .entry(socket_id)
+                    .or_insert_with(|| {
+                        LocalPlayer::new(
+                            socket_id,
+                            format!("local-keyboard-{index}"),
+                            format!("Keyboard {}", index + 1),
+                        )
+                    })
+                    .handle_action(*action, is_pressed);
+            }
+        }
+    }
+
+    fn open_controller(&mut self, device_index: u32) {
+        let Some(controller_subsystem) = &self.controller_subsystem else {
+            return;
+        };
+        match controller_subsystem.open(device_index) {
+            Ok(controller) => {
+                debug!("Game controller \"{}\" added.", controller.name());
+                self.controllers
+                    .insert(controller.instance_id(), controller);
+            }
+            Err(err) => warn!("Failed to open the game controller {device_index}: {err}."),
+        }
+    }
+
+    fn controller_player(&mut self, instance_id: u32) -> &mut LocalPlayer {
+        let socket_id = CONTROLLER_SOCKET_ID_BASE + instance_id;
+        self.players.entry(socket_id).or_insert_with(|| {
+            LocalPlayer::new(
+                socket_id,
+                format!("local-controller-{instance_id}"),
+                format!("Controller {}", instance_id + 1),
+            )
+        })
+    }
+}
+
+struct LocalPlayer {
+    socket_id: u32,
+    /// Inputs waiting to be sent to the game, the first ones join the game.
+    pending_inputs: VecDeque<RemoteInput>,
+    is_ready: bool,
+    pressed_directions: Vec<LocalAction>,
+    is_moving: bool,
+    stick_movement: (f64, f64),
+    stick_aim: (f64, f64),
+    aim: f64,
+    aim_rotation: f64,
+}
+
+impl LocalPlayer {
+    fn new(socket_id: u32, id: String, name: String) -> LocalPlayer {
+        LocalPlayer {
+            socket_id,
+            pending_inputs: VecDeque::from([
+                RemoteInput::ConfigurationInput(ConfigurationInput::PlayerId(id)),
+                RemoteInput::ConfigurationInput(ConfigurationInput::SetName(name)),
+            ]),
+            is_ready: false,
+            pressed_directions: Vec::new(),
+            is_moving: false,
+            stick_movement: (0., 0.),
+            stick_aim: (0., 0.),
+            aim: 0.,
+            aim_rotation: 0.,
+        }
+    }
+
+    fn handle_action(&mut self, action: LocalAction, is_pressed: bool) {
+        match action {
+            LocalAction::Up | LocalAction::Down | LocalAction::Left | LocalAction::Right => {
+                self.pressed_directions
+                    .retain(|direction| *direction != action);
+                if is_pressed {
+                    self.pressed_directions.push(action);
+                }
+                let (x, y) = self
+                    .pressed_directions
+                    .iter()
+                    .fold((0., 0.), |(x, y), direction| match direction {
+                        LocalAction::Up => (x, y - 1.),
+                        LocalAction::Down => (x, y + 1.),
+                        LocalAction::Left => (x - 1., y),
+                        _ => (x + 1., y),
+                    });
+                self.push_movement(x, y);
+            }
+            LocalAction::AimLeft if is_pressed => self.aim_rotation = -AIM_ROTATION_SPEED,
+            LocalAction::AimRight if is_pressed => self.aim_rotation = AIM_ROTATION_SPEED,
+            LocalAction::AimLeft | LocalAction::AimRight => self.aim_rotation = 0.,
+            _ if !is_pressed => {}
+            LocalAction::Shoot => {
+                // The game only shoots in the current aim, which is forgotten after each shot
+                self.push_game_input(GameInput::Aim(self.aim));
+                self.push_game_input(GameInput::Shoot);
+            }
+            LocalAction::Reload => self.push_game_input(GameInput::Reload),
+            LocalAction::UseAbility => self.push_game_input(GameInput::UseAbility),
+            LocalAction::ToggleReady => {
+                self.is_ready = !self.is_ready;
+                let input = match self.is_ready {
+                    true => ConfigurationInput::Ready,
+                    false => ConfigurationInput::NotReady,
+                };
+                self.pending_inputs
+                    .push_back(RemoteInput::ConfigurationInput(input));
+            }
+        }
+    }
+
+    fn set_stick_movement(&mut self, x: Option<f64>, y: Option<f64>) {
+        self.stick_movement = (
+            x.unwrap_or(self.stick_movement.0),
+            y.unwrap_or(self.stick_movement.1),
+        );
+        let (x, y) = self.stick_movement;
+        self.push_movement(x, y);
+    }
+
+    fn set_stick_aim(&mut self, x: Option<f64>, y: Option<f64>) {
+        self.stick_aim = (x.unwrap_or(self.stick_aim.0), y.unwrap_or(self.stick_aim.1));
+        let (x, y) = self.stick_aim;
+        if x.hypot(y) > STICK_DEAD_ZONE {
+            self.aim = y.atan2(x);
+            self.push_game_input(GameInput::Aim(self.aim));
+        }
+    }
+
+    fn rotate_aim(&mut self) {
+        if self.aim_rotation != 0. {
+            self.aim += self.aim_rotation;
+            self.push_game_input(GameInput::Aim(self.aim));
+        }
+    }
+
+    /// The y axis goes down, like on the screen.
+    fn push_movement(&mut self, x: f64, y: f64) {
+        if x.hypot(y) > STICK_DEAD_ZONE {
+            self.is_moving = true;
+            self.push_game_input(GameInput::Move(y.atan2(x)));
+        } else if self.is_moving {
+            self.is_moving = false;
+            self.push_game_input(GameInput::Stop);
+        }
+    }
+
+    /// A waiting movement or aim is replaced by the newer one, so the inputs don't pile up.
+    fn push_game_input(&mut self, input: GameInput) {
+        let replaces = |pending: &RemoteInput| {
+            matches!(
+                (pending, &input),
+                (
+                    RemoteInput::GameInput(GameInput::Move(_) | GameInput::Stop),
+                    GameInput::Move(_) | GameInput::Stop,
+                ) | (RemoteInput::GameInput(GameInput::Aim(_)), GameInput::Aim(_))
+            )
+        };
+        if let Some(pending) = self
+            .pending_inputs
+            .iter_mut()
+            .find(|pending| replaces(pending))
+        {
+            *pending = RemoteInput::GameInput(input);
+        } else {
+            self.pending_inputs.push_back(RemoteInput::GameInput(input));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Mod;
+
+    use super::*;
+
+    fn key_event(keycode: Keycode, is_pressed: bool) -> Event {
+        match is_pressed {
+            true => Event::KeyDown {
+                timestamp: 0,
+                window_id: 0,
+                keycode: Some(keycode),
+                scancode: None,
+                keymod: Mod::NOMOD,
+                repeat: false,
+            },
+            false => Event::KeyUp {
+                timestamp: 0,
+                window_id: 0,
+                keycode: Some(keycode),
+                scancode: None,
+                keymod: Mod::NOMOD,
+                repeat: false,
+            },
+        }
+    }
+
+    fn drain_game_inputs(local_players: &mut LocalPlayers) -> Vec<GameInput> {
+        let mut game_inputs = Vec::new();
+        loop {
+            let inputs = local_players.next_inputs();
+            if inputs.is_empty() {
+                return game_inputs;
+            }
+            game_inputs.extend(
+                inputs
+                    .into_iter()
+                    .filter_map(|input| match input.remote_input {
+                        RemoteInput::GameInput(game_input) => Some(game_input),
+                        _ => None,
+                    }),
+            );
+        }
+    }
+
+    #[test]
+    fn each_shot_is_preceded_by_the_aim() {
+        let mut local_players = LocalPlayers::new(None);
+
+        for _ in 0..2 {
+            local_players.handle_event(&key_event(Keycode::Space, true));
+            local_players.handle_event(&key_event(Keycode::Space, false));
+
+            let game_inputs = drain_game_inputs(&mut local_players);
+            assert!(
+                matches!(
+                    game_inputs.as_slice(),
+                    [GameInput::Aim(aim), GameInput::Shoot] if *aim == 0.
+                ),
+                "unexpected inputs {game_inputs:?}"
+            );
+        }
+    }
+}
@@ -1,12 +1,20 @@
-use futures_channel::mpsc::unbounded;
-use game::components::{Bullet, Circle, Movement, Player, Position};
+use futures_channel::mpsc::{unbounded, UnboundedSender};
+use game::components::{Bullet, Circle, Mine, Movement, Pickup, Player, Position};
+use game::configuration::{get_configuration, Arguments, SignalingMode, WindowMode};
 use game::game::{MessageToGame, RoomId};
-use game::remotes::PlayerInput;
+use game::game_modes::GameModeKind;
+use game::lan_signaling::LanSignaling;
+use game::local_players::LocalPlayers;
+use game::remotes::{MessageToPlayer, NetworkStats, ReceivedInputs};
 use game::render::renderer::SystemData;
 use game::startup::setup_logger;
 use game::state::game_state::{Assets, Phase, State};
-use game::state::Map;
-use game::systems::{HandleInputs, RetrievePlayerForInputs};
+use game::state::{CollisionGrid, FeedbackPatterns, Map, RoundTimer, Rules};
+use game::systems::{
+    HandleAbilities, HandleCollisions, HandleCooldowns, HandleDeaths, HandleGameMode, HandlePhase,
+    HandlePickups, HandleShooting, HandleSuddenDeath, MapGameInputs, MoveEntities,
+    RetrievePlayerForInputs, SendFeedback, SendPlayerStates, UpdateCollisionGrid, UpdateKillFeed,
+};
 use game::{players_connector, render::renderer, room_code, server_communicator};
 use players_connector::PlayersConnector;
 use sdl2::event::Event;
@@ -19,49 +27,75 @@ use room_code::RoomCode;
 use sdl2::image::{self, InitFlag};
 use sdl2::ttf::{Font, Sdl2TtfContext};
 use sdl2::video::{Window, WindowContext};
-use server_communicator::ServerCommunicator;
-use specs::{Builder, Dispatcher, DispatcherBuilder, World, WorldExt};
+use server_communicator::{ServerCommunicator, ServerStatus};
+use specs::{Dispatcher, DispatcherBuilder, World, WorldExt};
 use std::env;
 use std::time::Duration;
-use tokio::spawn;
+use tokio::{spawn, time::timeout};
 use tracing::debug;
 
+/// Time given to the players connector to close the peer connections when the game stops.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, Copy)]
 struct Direction(f64);
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        setup_logger(&args[1]);
-    } else {
-        setup_logger("info");
+    let arguments = Arguments::parse(env::args().skip(1)).map_err(|err| err.to_string())?;
+    if arguments.show_help {
+        println!("{}", Arguments::usage());
+        return Ok(());
     }
+    let settings = get_configuration(arguments).map_err(|err| err.to_string())?;
+    setup_logger(&settings.log_level);
 
     let (sender_to_server, receiver_server) = unbounded();
     let (sender_to_player_connector, receiver_player_connector) = unbounded();
     let (sender_to_game, mut receiver_game) = unbounded();
+    let (sender_to_players, receiver_players) = unbounded();
 
-    let mut players_connector = PlayersConnector::new(
+    let players_connector = PlayersConnector::new(
         sender_to_server.clone(),
         sender_to_game.clone(),
         receiver_player_connector,
+        receiver_players,
+        settings.ice_servers,
     );
-    spawn(async move { players_connector.start().await });
+    let players_connector = spawn(players_connector.start());
 
-    let server_communicator = ServerCommunicator::new(
-        sender_to_game,
-        sender_to_player_connector,
-        "ws://tank-game.flmichel.duckdns.org/ws",
-    );
-    spawn(async move { server_communicator.start(receiver_server).await });
+    let join_url = match settings.signaling {
+        SignalingMode::Server => {
+            let server_communicator = ServerCommunicator::new(
+                sender_to_game,
+                sender_to_player_connector,
+                settings.server_url,
+            );
+            spawn(async move { server_communicator.start(receiver_server).await });
+            settings.join_url
+        }
+        SignalingMode::Lan => {
+            let lan_signaling = LanSignaling::new(
+                sender_to_game,
+                sender_to_player_connector,
+                settings.lan_host,
+                settings.lan_port,
+                settings.remote_bundle_path,
+            );
+            let join_url = lan_signaling.join_url();
+            spawn(async move { lan_signaling.start(receiver_server).await });
+            join_url
+        }
+    };
 
-    let mut assets = load_assets();
+    let mut assets = load_assets(settings.window_mode);
 
-    let mut world = create_world(assets.canvas.window());
-    let mut dispatcher = create_dispatcher();
+    let mut world = create_world(assets.canvas.window(), &settings.map_path, settings.rules);
+    let mut dispatcher = create_dispatcher(sender_to_players);
+    dispatcher.setup(&mut world);
 
     let mut event_pump = assets.sdl_context.event_pump()?;
+    let mut local_players = LocalPlayers::new(assets.sdl_context.game_controller().ok());
 
     let ttf_context = sdl2::ttf::init().unwrap();
     let font = load_font(&ttf_context);
@@ -78,6 +112,7 @@ async fn main() -> Result<(), String> {
 
     'running: loop {
         for event in event_pump.poll_iter() {
+            local_players.handle_event(&event);
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -86,25 +121,45 @@ async fn main() -> Result<(), String> {
                 } => {
                     break 'running;
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    ..
+                } => {
+                    let mut game_state = world.write_resource::<State>();
+                    game_state.show_debug_overlay = !game_state.show_debug_overlay;
+                }
                 _ => {}
             }
         }
 
-        if let Ok(Some(message)) = receiver_game.try_next() {
+        while let Ok(Some(message)) = receiver_game.try_next() {
             match message {
                 MessageToGame::RoomId(id) => {
                     debug!("Received room id \"{}\" from server.", id.0);
                     let mut game_state = world.write_resource::<State>();
-                    game_state.room_code = RoomCode::new(
-                        format!("http://192.168.0.108:8080/?room-id={}", id.0).to_owned(),
-                    );
+                    game_state.room_code = RoomCode::new(format!("{}{}", join_url, id.0));
                 }
                 MessageToGame::PlayerInput(player_input) => {
-                    world.create_entity().with(player_input).build();
+                    world.write_resource::<ReceivedInputs>().push(player_input);
+                }
+                MessageToGame::ServerStatus(status) => {
+                    world.write_resource::<State>().server_status = status;
+                }
+                MessageToGame::ConnectionStats { socket_id, stats } => {
+                    world
+                        .write_resource::<NetworkStats>()
+                        .update(socket_id, stats);
+                }
+                MessageToGame::ConnectionClosed(socket_id) => {
+                    world.write_resource::<NetworkStats>().remove(socket_id);
                 }
             }
         }
 
+        for player_input in local_players.next_inputs() {
+            world.write_resource::<ReceivedInputs>().push(player_input);
+        }
+
         dispatcher.dispatch(&mut world);
         world.maintain();
 
@@ -124,35 +179,102 @@ async fn main() -> Result<(), String> {
         ));
     }
 
+    // Without its senders, the players connector stops and closes the peer connections
+    drop(dispatcher);
+    if timeout(SHUTDOWN_TIMEOUT, players_connector).await.is_err() {
+        debug!("The players connector did not stop in time.");
+    }
+
     Ok(())
 }
 
-fn create_world(window: &Window) -> World {
+fn create_world(window: &Window, map_path: &str, rules: Rules) -> World {
     let mut world = World::new();
     world.register::<RoomId>();
-    world.register::<PlayerInput>();
     world.register::<Position>();
     world.register::<Movement>();
     world.register::<Circle>();
     world.register::<Player>();
     world.register::<Bullet>();
+    world.register::<Pickup>();
+    world.register::<Mine>();
 
+    let map = Map::from_file(map_path, window.size()).unwrap();
     let game_state = State {
         room_code: RoomCode::new("Error, the game could not connect to server".to_owned()),
         phase: Phase::BeforeNextGame,
         number_of_ready_players: 0,
-        map: Map::from_file("assets/map.txt", window.size()).unwrap(),
         resolution: window.size(),
+        rules,
+        last_winner: None,
+        game_mode: GameModeKind::LastTankStanding.create(),
+        show_debug_overlay: false,
+        server_status: ServerStatus::Connecting,
     };
+    world.insert(CollisionGrid::new(map.width(), map.height()));
+    world.insert(map);
+    world.insert(RoundTimer::default());
     world.insert(game_state);
+    world.insert(NetworkStats::default());
+    world.insert(ReceivedInputs::default());
 
     world
 }
 
-fn create_dispatcher() -> Dispatcher<'static, 'static> {
+fn create_dispatcher(
+    sender_to_players: UnboundedSender<MessageToPlayer>,
+) -> Dispatcher<'static, 'static> {
+    // Most systems write the players, only the systems using different data run concurrently,
+    // e.g. the sudden death with the update of the collision grid
     let dispatcher = DispatcherBuilder::new()
         .with(RetrievePlayerForInputs, "RetrievePlayerForInputs", &[])
-        .with(HandleInputs, "HandleInputs", &["RetrievePlayerForInputs"])
+        .with(HandlePhase, "HandlePhase", &["RetrievePlayerForInputs"])
+        .with(MapGameInputs, "MapGameInputs", &["HandlePhase"])
+        .with(MoveEntities, "MoveEntities", &["MapGameInputs"])
+        .with(
+            UpdateCollisionGrid,
+            "UpdateCollisionGrid",
+            &["MoveEntities"],
+        )
+        .with(HandleSuddenDeath, "HandleSuddenDeath", &["MoveEntities"])
+        .with(
+            HandleCollisions,
+            "HandleCollisions",
+            &["UpdateCollisionGrid"],
+        )
+        .with(
+            HandlePickups::default(),
+            "HandlePickups",
+            &["UpdateCollisionGrid"],
+        )
+        .with(HandleShooting, "HandleShooting", &["HandleCollisions"])
+        .with(HandleCooldowns, "HandleCooldowns", &["HandleShooting"])
+        .with(HandleAbilities, "HandleAbilities", &["HandleCollisions"])
+        .with(
+            HandleDeaths::default(),
+            "HandleDeaths",
+            &["HandleAbilities", "HandleSuddenDeath"],
+        )
+        .with(
+            HandleGameMode,
+            "HandleGameMode",
+            &["HandleDeaths", "HandleCooldowns", "HandlePickups"],
+        )
+        .with(
+            UpdateKillFeed::default(),
+            "UpdateKillFeed",
+            &["HandleGameMode"],
+        )
+        .with(
+            SendFeedback::new(sender_to_players.clone(), FeedbackPatterns::default()),
+            "SendFeedback",
+            &["HandleGameMode"],
+        )
+        .with(
+            SendPlayerStates::new(sender_to_players),
+            "SendPlayerStates",
+            &["HandleGameMode"],
+        )
         .build();
 
     dispatcher
@@ -164,7 +286,7 @@ fn load_font(ttf_context: &Sdl2TtfContext) -> Font {
     ttf_context.load_font(font_path, font_size).unwrap()
 }
 
-fn load_assets<'a>() -> Assets {
+fn load_assets<'a>(window_mode: WindowMode) -> Assets {
     let sdl_context = sdl2::init().expect("failed to create context");
     let video_subsystem = sdl_context
         .video()
@@ -175,9 +297,17 @@ fn load_assets<'a>() -> Assets {
     // temporary value and drop it right away!
     let _image_context = image::init(InitFlag::PNG | InitFlag::JPG).expect("failed to get image");
 
-    let window = video_subsystem
-        .window("tank game", 800, 600)
-        .fullscreen_desktop() // Set fullscreen mode
+    let mut window = video_subsystem.window("tank game", 800, 600);
+    match window_mode {
+        WindowMode::Fullscreen => {
+            window.fullscreen();
+        }
+        WindowMode::FullscreenDesktop => {
+            window.fullscreen_desktop();
+        }
+        WindowMode::Windowed => {}
+    }
+    let window = window
         .position_centered()
         .build()
         .expect("could not initialize video subsystem");
@@ -1,6 +1,20 @@
-use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
-use std::sync::Arc;
-use tracing::{debug, trace};
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::StreamExt;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tokio::{
+    runtime::Handle,
+    select, spawn,
+    task::spawn_blocking,
+    time::{interval, MissedTickBehavior},
+};
+use tracing::{debug, trace, warn};
 use webrtc::{
     api::{
         interceptor_registry::register_default_interceptors,
@@ -8,7 +22,10 @@ use webrtc::{
         APIBuilder, API,
     },
     data_channel::{data_channel_message::DataChannelMessage, RTCDataChannel},
-    ice_transport::ice_server::RTCIceServer,
+    ice_transport::{
+        ice_candidate::{RTCIceCandidate, RTCIceCandidateInit},
+        ice_server::RTCIceServer,
+    },
     interceptor::registry::Registry,
     peer_connection::{
         configuration::RTCConfiguration, peer_connection_state::RTCPeerConnectionState,
@@ -18,64 +35,306 @@ use webrtc::{
 };
 
 use crate::{
+    configuration::IceServerSettings,
+    error::{Error, ErrorKind},
     game::MessageToGame,
-    remotes::{PlayerInput, RemoteInput},
-    server_communicator::{MessageToServer, SdpMessage},
+    remotes::{
+        ConnectionStats, Envelope, GameInput, LatencyTracker, MessageToPlayer, MessageToRemote,
+        PlayerInput, RemoteInput, RemoteMessage, PING_INTERVAL, PROTOCOL_VERSION,
+    },
+    server_communicator::{
+        IceCandidateMessage, MessageToServer, SdpErrorMessage, SdpMessage, SignalingMessage,
+    },
     signal::{decode, encode},
 };
 
 pub struct PlayersConnector {
+    receiver: UnboundedReceiver<SignalingMessage>,
+    receiver_from_game: UnboundedReceiver<MessageToPlayer>,
+    /// Socket ids of the peer connections which failed or were closed.
+    receiver_closed: UnboundedReceiver<u32>,
+    context: ConnectorContext,
+    configuration: RTCConfiguration,
+    webrtc_util: Arc<WebRTCUtil>,
+    next_player_socket_id: u32,
+}
+
+/// Connections of the remotes, shared by the players connector with the tasks answering the
+/// offers.
+#[derive(Clone)]
+struct ConnectorContext {
     sender_to_server: UnboundedSender<MessageToServer>,
     sender_to_game: UnboundedSender<MessageToGame>,
-    receiver: UnboundedReceiver<SdpMessage>,
-    webrtc_util: WebRTCUtil,
+    sender_closed: UnboundedSender<u32>,
+    peer_connections: PeerConnections,
+    data_channels: DataChannels,
+    sessions: Sessions,
+}
+
+/// Peer connections of the remotes by socket id.
+type PeerConnections = Arc<Mutex<HashMap<u32, RegisteredPeerConnection>>>;
+/// Data channels of the connected remotes by socket id, used to send them messages.
+type DataChannels = Arc<Mutex<HashMap<u32, RemoteChannels>>>;
+/// Sessions of the connected remotes by socket id.
+type Sessions = Arc<Mutex<HashMap<u32, Arc<RemoteSession>>>>;
+
+struct RegisteredPeerConnection {
+    /// Id of the offer which started the connection, to add the trickled candidates.
+    signaling_id: u32,
+    peer_connection: Arc<RTCPeerConnection>,
+}
+
+#[derive(Default)]
+struct RemoteChannels {
+    reliable: Option<Arc<RTCDataChannel>>,
+    continuous: Option<Arc<RTCDataChannel>>,
+}
+
+/// Answers an invalid offer with an error, so its remote stops waiting for the answer.
+fn reject_offer(context: &ConnectorContext, signaling_id: u32, err: Error) {
+    warn!("Rejected the offer {signaling_id}: {err}.");
+    let reply = MessageToServer::SdpError(SdpErrorMessage {
+        id: signaling_id,
+        error: err.to_string(),
+    });
+    if let Err(err) = context.sender_to_server.unbounded_send(reply) {
+        debug!("failed to send message {}", err.to_string());
+    }
+}
+
+/// Label of the unordered and unreliable data channel used by the remotes for their `Move` and
+/// `Aim` inputs, any other data channel is the reliable one.
+const CONTINUOUS_CHANNEL_LABEL: &str = "continuous-inputs";
+
+/// Protocol state of a remote, negotiated by its handshake and shared by its data channels.
+#[derive(Default)]
+struct RemoteSession {
+    handshake_done: AtomicBool,
+    binary_inputs: AtomicBool,
+    last_movement_sequence: AtomicU32,
+    last_aim_sequence: AtomicU32,
+    latency: Mutex<LatencyTracker>,
+}
+
+impl RemoteSession {
+    fn parse(&self, msg: &DataChannelMessage) -> Result<Envelope, Error> {
+        if msg.is_string {
+            let text = std::str::from_utf8(&msg.data)
+                .map_err(|err| Error::from(err, ErrorKind::InvalidMessage))?;
+            Envelope::parse(text)
+        } else if self.binary_inputs.load(Ordering::SeqCst) {
+            Envelope::parse_binary(&msg.data)
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidMessage,
+                "binary inputs were not negotiated",
+            ))
+        }
+    }
+
+    /// Whether a newer input of the same kind was already received, the inputs sent on the
+    /// unordered channel can arrive out of order.
+    fn is_stale(&self, envelope: &Envelope) -> bool {
+        let last_sequence = match envelope.message {
+            RemoteMessage::Game(GameInput::Move(_) | GameInput::Stop) => {
+                &self.last_movement_sequence
+            }
+            RemoteMessage::Game(GameInput::Aim(_)) => &self.last_aim_sequence,
+            _ => return false,
+        };
+        envelope
+            .sequence
+            .is_some_and(|sequence| last_sequence.fetch_max(sequence, Ordering::SeqCst) >= sequence)
+    }
 }
 
 struct WebRTCUtil {
     api: API,
-    configuration: RTCConfiguration,
 }
 
 impl WebRTCUtil {
+    /// Answers an offer in its own task, so a slow remote doesn't delay the other ones.
+    async fn answer_offer(
+        &self,
+        configuration: RTCConfiguration,
+        offer: SdpMessage,
+        context: ConnectorContext,
+        socket_id: u32,
+    ) {
+        debug!("Received an offer in players_connector.");
+        let (peer_connection, description) = match self
+            .start_peer_connection(configuration, &offer, context.clone(), socket_id)
+            .await
+        {
+            Ok((peer_connection, description)) => (Arc::new(peer_connection), description),
+            Err(err) => {
+                reject_offer(&context, offer.id, err);
+                return;
+            }
+        };
+        context.peer_connections.lock().unwrap().insert(
+            socket_id,
+            RegisteredPeerConnection {
+                signaling_id: offer.id,
+                peer_connection: Arc::clone(&peer_connection),
+            },
+        );
+        if let Err(err) = peer_connection.set_remote_description(description).await {
+            let err = Error::from(err, ErrorKind::InvalidMessage)
+                .explain("the offer could not be set as remote description");
+            reject_offer(&context, offer.id, err);
+            // The players connector closes the peer connection like a failed one
+            let _ = context.sender_closed.unbounded_send(socket_id);
+            return;
+        }
+        let answer = self.create_answer(&peer_connection).await;
+        debug!("Received an answer in players_connector.");
+        let answer = MessageToServer::SdpAnswer(SdpMessage {
+            data: encode(&answer),
+            id: offer.id,
+        });
+
+        debug!("Try to send the answer back to the server_communicator.");
+        match context.sender_to_server.unbounded_send(answer) {
+            Ok(()) => debug!("The message was successfully sent."),
+            Err(err) => debug!("failed to send message {}", err.to_string()),
+        }
+    }
+
+    /// Creates the peer connection of an offer, returned with the session description of the
+    /// offer which is set once the peer connection is registered. Fails when the offer can't
+    /// be decoded, before anything is created.
     async fn start_peer_connection(
         &self,
-        offer: String,
-        sender_to_game: UnboundedSender<MessageToGame>,
+        configuration: RTCConfiguration,
+        offer: &SdpMessage,
+        context: ConnectorContext,
         socket_id: u32,
-    ) -> RTCPeerConnection {
+    ) -> Result<(RTCPeerConnection, RTCSessionDescription), Error> {
+        let description = decode(offer.data.as_str())
+            .map_err(|err| {
+                Error::from(err, ErrorKind::InvalidMessage).explain("the offer is not base64")
+            })
+            .and_then(|offer| {
+                serde_json::from_str::<RTCSessionDescription>(&offer).map_err(|err| {
+                    Error::from(err, ErrorKind::InvalidMessage)
+                        .explain("the offer is not a session description")
+                })
+            })?;
+
         let peer_connection = self
             .api
-            .new_peer_connection(self.configuration.clone())
+            .new_peer_connection(configuration)
             .await
             .expect("peer connection creation failed");
 
+        let sender_closed = context.sender_closed;
         peer_connection.on_peer_connection_state_change(Box::new(
             move |s: RTCPeerConnectionState| {
                 debug!("Peer Connection State with socketId \"{socket_id}\" has changed: {s}.");
 
-                if s == RTCPeerConnectionState::Failed {
+                if s == RTCPeerConnectionState::Failed || s == RTCPeerConnectionState::Closed {
                     debug!(
-                        "Peer Connection with socketId \"{socket_id}\" has gone to failed exiting."
+                        "Peer Connection with socketId \"{socket_id}\" has gone to {s}, closing it."
                     );
+                    // The players connector is gone when it closed the connection itself
+                    let _ = sender_closed.unbounded_send(socket_id);
                 }
 
                 Box::pin(async {})
             },
         ));
 
+        // Trickle the candidates to the remote as soon as they are gathered
+        let signaling_id = offer.id;
+        let sender_to_server = context.sender_to_server;
+        peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let candidate = candidate.map(|candidate| {
+                let candidate = candidate
+                    .to_json()
+                    .expect("failed to convert ice candidate");
+                serde_json::to_string(&candidate).expect("failed to serialize ice candidate")
+            });
+            let message = MessageToServer::IceCandidate(IceCandidateMessage {
+                id: signaling_id,
+                candidate,
+            });
+            if let Err(err) = sender_to_server.unbounded_send(message) {
+                debug!("failed to send message {}", err.to_string());
+            }
+            Box::pin(async {})
+        }));
+
+        let session = Arc::new(RemoteSession::default());
+        let data_channels = context.data_channels;
+        let sender_to_game = context.sender_to_game;
+        context
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(socket_id, Arc::clone(&session));
         peer_connection.on_data_channel(Box::new(move |d: Arc<RTCDataChannel>| {
             let d_label = d.label().to_owned();
             let d_id = d.id();
             debug!("New DataChannel {d_label} {d_id} with socket id \"{socket_id}\".");
+            {
+                let mut data_channels = data_channels.lock().unwrap();
+                let channels = data_channels.entry(socket_id).or_default();
+                if d_label == CONTINUOUS_CHANNEL_LABEL {
+                    channels.continuous = Some(Arc::clone(&d));
+                } else {
+                    channels.reliable = Some(Arc::clone(&d));
+                }
+            }
 
             let sender_to_game = sender_to_game.clone();
+            let session = Arc::clone(&session);
             // Register channel opening handling
             Box::pin(async move {
-                // Register text message handling
+                // Weak reference, the data channel owns the message handler
+                let data_channel = Arc::downgrade(&d);
+                // Register message handling
                 d.on_message(Box::new(move |msg: DataChannelMessage| {
-                    let msg_str = String::from_utf8(msg.data.to_vec()).unwrap();
-                    trace!("Message from DataChannel '{d_label}' {socket_id}: '{msg_str}'");
-                    let remote_input: RemoteInput = serde_json::from_str(&msg_str).unwrap();
+                    trace!("Message from DataChannel '{d_label}' {socket_id}: {msg:?}");
+                    let envelope = match session.parse(&msg) {
+                        Ok(envelope) => envelope,
+                        Err(err) => {
+                            warn!("Rejected a message from socket id \"{socket_id}\": {err}.");
+                            return Box::pin(async {});
+                        }
+                    };
+                    if session.is_stale(&envelope) {
+                        trace!("Dropped a stale input from socket id \"{socket_id}\".");
+                        return Box::pin(async {});
+                    }
+                    let remote_input = match envelope.message {
+                        RemoteMessage::Handshake(options) => {
+                            let binary_inputs = options.unwrap_or_default().binary_inputs;
+                            debug!("Handshake with socket id \"{socket_id}\", binary inputs: {binary_inputs}.");
+                            session.handshake_done.store(true, Ordering::SeqCst);
+                            session.binary_inputs.store(binary_inputs, Ordering::SeqCst);
+                            let data_channel = data_channel.clone();
+                            return Box::pin(async move {
+                                if let Some(data_channel) = data_channel.upgrade() {
+                                    let answer = MessageToRemote::Handshake {
+                                        version: PROTOCOL_VERSION,
+                                        binary_inputs,
+                                    };
+                                    send_message(&data_channel, socket_id, &answer).await;
+                                }
+                            });
+                        }
+                        _ if !session.handshake_done.load(Ordering::SeqCst) => {
+                            warn!("Rejected a message from socket id \"{socket_id}\": sent before the handshake.");
+                            return Box::pin(async {});
+                        }
+                        RemoteMessage::Pong(id) => {
+                            session.latency.lock().unwrap().pong(id, Instant::now());
+                            return Box::pin(async {});
+                        }
+                        RemoteMessage::Game(input) => RemoteInput::GameInput(input),
+                        RemoteMessage::Configuration(input) => RemoteInput::ConfigurationInput(input),
+                    };
 
                     sender_to_game
                         .unbounded_send(MessageToGame::PlayerInput(PlayerInput {
@@ -88,14 +347,7 @@ impl WebRTCUtil {
             })
         }));
 
-        let offer = serde_json::from_str::<RTCSessionDescription>(&decode(offer.as_str()).unwrap())
-            .unwrap();
-        peer_connection
-            .set_remote_description(offer)
-            .await
-            .expect("failed to set remote sessionDescription");
-
-        peer_connection
+        Ok((peer_connection, description))
     }
 
     async fn create_answer(&self, peer_connection: &RTCPeerConnection) -> String {
@@ -104,20 +356,13 @@ impl WebRTCUtil {
             .await
             .expect("failed to create answer");
 
-        // Create channel that is blocked until ICE Gathering is complete
-        let mut gather_complete = peer_connection.gathering_complete_promise().await;
-
-        // Sets the LocalDescription, and starts our UDP listeners
+        // Sets the LocalDescription, and starts our UDP listeners, the gathered candidates are
+        // trickled to the remote
         peer_connection
             .set_local_description(answer)
             .await
             .expect("failed to set local description");
 
-        // Block until ICE Gathering is complete, disabling trickle ICE
-        // we do this because we only can exchange one signaling message
-        // in a production application you should exchange ICE Candidates via OnICECandidate
-        let _ = gather_complete.recv().await;
-
         let answer = peer_connection
             .local_description()
             .await
@@ -131,7 +376,9 @@ impl PlayersConnector {
     pub fn new(
         sender_to_server: UnboundedSender<MessageToServer>,
         sender_to_game: UnboundedSender<MessageToGame>,
-        receiver: UnboundedReceiver<SdpMessage>,
+        receiver: UnboundedReceiver<SignalingMessage>,
+        receiver_from_game: UnboundedReceiver<MessageToPlayer>,
+        ice_servers: Vec<IceServerSettings>,
     ) -> Self {
         let mut m = MediaEngine::default();
         let fastest_codec = RTCRtpCodecParameters {
@@ -157,48 +404,236 @@ impl PlayersConnector {
             .build();
 
         let configuration = RTCConfiguration {
-            ice_servers: vec![RTCIceServer {
-                urls: vec!["stun:stun.l.google.com:19302".to_owned()],
-                ..Default::default()
-            }],
+            ice_servers: ice_servers.into_iter().map(RTCIceServer::from).collect(),
             ..Default::default()
         };
 
+        let (sender_closed, receiver_closed) = unbounded();
         Self {
-            sender_to_server,
-            sender_to_game,
             receiver,
-            webrtc_util: WebRTCUtil { api, configuration },
+            receiver_from_game,
+            receiver_closed,
+            context: ConnectorContext {
+                sender_to_server,
+                sender_to_game,
+                sender_closed,
+                peer_connections: Arc::new(Mutex::new(HashMap::new())),
+                data_channels: Arc::new(Mutex::new(HashMap::new())),
+                sessions: Arc::new(Mutex::new(HashMap::new())),
+            },
+            configuration,
+            webrtc_util: Arc::new(WebRTCUtil { api }),
+            next_player_socket_id: 0,
         }
     }
 
-    pub async fn start(&mut self) {
-        let mut next_player_socket_id: u32 = 0;
+    /// Handles the signaling messages and the messages of the game until one of them stops,
+    /// then closes the peer connections of the remotes.
+    pub async fn start(mut self) {
+        let mut ping_interval = interval(PING_INTERVAL);
+        ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
-            if let Ok(Some(offer)) = self.receiver.try_next() {
-                debug!("Received an offer in players_connector.");
-                let peer_connection = self
-                    .webrtc_util
-                    .start_peer_connection(
-                        offer.data,
-                        self.sender_to_game.clone(),
-                        next_player_socket_id,
-                    )
-                    .await;
-                next_player_socket_id += 1;
-                let answer = self.webrtc_util.create_answer(&peer_connection).await;
-                debug!("Received an answer in players_connector.");
-                let answer = MessageToServer::SdpAnswer(SdpMessage {
-                    data: encode(&answer),
-                    id: offer.id,
-                });
-
-                debug!("Try to send the answer back to the server_communicator.");
-                match self.sender_to_server.unbounded_send(answer) {
-                    Ok(()) => debug!("The message was successfully sent."),
-                    Err(err) => debug!("failed to send message {}", err.to_string()),
+            select! {
+                message = self.receiver.next() => match message {
+                    Some(SignalingMessage::Offer(offer)) => self.answer_offer(offer),
+                    Some(SignalingMessage::IceCandidate(candidate)) => {
+                        self.add_ice_candidate(candidate).await;
+                    }
+                    Some(SignalingMessage::IceServers(ice_servers)) => {
+                        self.add_ice_servers(ice_servers);
+                    }
+                    None => break,
+                },
+                message = self.receiver_from_game.next() => match message {
+                    Some(message) => self.send_to_remote(message).await,
+                    None => break,
+                },
+                Some(socket_id) = self.receiver_closed.next() => {
+                    self.close_peer_connection(socket_id).await;
                 }
+                _ = ping_interval.tick() => self.ping_remotes().await,
+            }
+        }
+
+        debug!("Closing the peer connections of the remotes.");
+        let socket_ids: Vec<u32> = self
+            .context
+            .peer_connections
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect();
+        for socket_id in socket_ids {
+            self.close_peer_connection(socket_id).await;
+        }
+    }
+
+    fn answer_offer(&mut self, offer: SdpMessage) {
+        let socket_id = self.next_player_socket_id;
+        self.next_player_socket_id += 1;
+        let webrtc_util = Arc::clone(&self.webrtc_util);
+        let configuration = self.configuration.clone();
+        let context = self.context.clone();
+        spawn(async move {
+            webrtc_util
+                .answer_offer(configuration, offer, context, socket_id)
+                .await
+        });
+    }
+
+    /// Forgets a remote and releases its peer connection.
+    async fn close_peer_connection(&self, socket_id: u32) {
+        self.context
+            .data_channels
+            .lock()
+            .unwrap()
+            .remove(&socket_id);
+        self.context.sessions.lock().unwrap().remove(&socket_id);
+        let registered = self
+            .context
+            .peer_connections
+            .lock()
+            .unwrap()
+            .remove(&socket_id);
+        let Some(registered) = registered else {
+            return;
+        };
+        debug!("Closing the peer connection with socket id \"{socket_id}\".");
+        if let Err(err) = self
+            .context
+            .sender_to_game
+            .unbounded_send(MessageToGame::ConnectionClosed(socket_id))
+        {
+            debug!("failed to send message {}", err.to_string());
+        }
+        // The closing future of the peer connection is not `Send`, it can't run in this task
+        let runtime = Handle::current();
+        let closing = spawn_blocking(move || runtime.block_on(registered.peer_connection.close()));
+        if let Ok(Err(err)) = closing.await {
+            debug!("Failed to close the peer connection with socket id \"{socket_id}\": {err}.");
+        }
+    }
+
+    /// Adds the ice servers provided by the server to the configured ones, for the next peer
+    /// connections.
+    fn add_ice_servers(&mut self, ice_servers: Vec<IceServerSettings>) {
+        let configured_servers = &mut self.configuration.ice_servers;
+        for ice_server in ice_servers.into_iter().map(RTCIceServer::from) {
+            let is_configured = configured_servers
+                .iter()
+                .any(|configured_server| configured_server.urls == ice_server.urls);
+            if !is_configured {
+                debug!(
+                    "Added the ice servers {:?} from the server.",
+                    ice_server.urls
+                );
+                configured_servers.push(ice_server);
             }
         }
     }
+
+    /// Adds a candidate trickled by the remote to the peer connection of its offer.
+    async fn add_ice_candidate(&self, message: IceCandidateMessage) {
+        let peer_connection = self
+            .context
+            .peer_connections
+            .lock()
+            .unwrap()
+            .values()
+            .find(|registered| registered.signaling_id == message.id)
+            .map(|registered| Arc::clone(&registered.peer_connection));
+        let Some(peer_connection) = peer_connection else {
+            debug!(
+                "No peer connection for the ice candidate of offer {}.",
+                message.id
+            );
+            return;
+        };
+        // The remote doesn't need to signal the end of its candidates
+        let Some(candidate) = message.candidate else {
+            return;
+        };
+        let result =
+            serde_json::from_str::<RTCIceCandidateInit>(&candidate).map_err(|err| err.to_string());
+        let result = match result {
+            Ok(candidate) => peer_connection
+                .add_ice_candidate(candidate)
+                .await
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            debug!(
+                "Failed to add the ice candidate of offer {}: {}.",
+                message.id, err
+            );
+        }
+    }
+
+    /// Pings the remotes on their continuous channel, where packets can be lost, and reports
+    /// their connection statistics to the game.
+    async fn ping_remotes(&self) {
+        let now = Instant::now();
+        let pings: Vec<(u32, u32, ConnectionStats)> = self
+            .context
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, session)| session.handshake_done.load(Ordering::SeqCst))
+            .map(|(socket_id, session)| {
+                let mut latency = session.latency.lock().unwrap();
+                (*socket_id, latency.ping(now), latency.stats())
+            })
+            .collect();
+
+        for (socket_id, ping_id, stats) in pings {
+            if let Err(err) = self
+                .context
+                .sender_to_game
+                .unbounded_send(MessageToGame::ConnectionStats { socket_id, stats })
+            {
+                debug!("failed to send message {}", err.to_string());
+            }
+            let data_channel = self
+                .context
+                .data_channels
+                .lock()
+                .unwrap()
+                .get(&socket_id)
+                .and_then(|channels| {
+                    channels
+                        .continuous
+                        .clone()
+                        .or_else(|| channels.reliable.clone())
+                });
+            if let Some(data_channel) = data_channel {
+                send_message(&data_channel, socket_id, &MessageToRemote::Ping(ping_id)).await;
+            }
+        }
+    }
+
+    async fn send_to_remote(&self, message: MessageToPlayer) {
+        let data_channel = self
+            .context
+            .data_channels
+            .lock()
+            .unwrap()
+            .get(&message.socket_id)
+            .and_then(|channels| channels.reliable.clone());
+        let Some(data_channel) = data_channel else {
+            trace!("No data channel with socket id \"{}\".", message.socket_id);
+            return;
+        };
+
+        send_message(&data_channel, message.socket_id, &message.message).await;
+    }
+}
+
+async fn send_message(data_channel: &RTCDataChannel, socket_id: u32, message: &MessageToRemote) {
+    let text = serde_json::to_string(message).expect("failed to serialize message");
+    if let Err(err) = data_channel.send_text(text).await {
+        debug!("Failed to send a message to socket id \"{socket_id}\": {err}.");
+    }
 }
@@ -0,0 +1,108 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Interval between two pings sent to each remote.
+pub const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// A ping without pong after this delay is counted as lost.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Number of last pings used to compute the packet loss.
+const LOSS_WINDOW: usize = 20;
+
+/// Connection quality of a remote, measured with pings over its data channels.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ConnectionStats {
+    /// Smoothed round trip time in milliseconds.
+    pub rtt_ms: f64,
+    /// Mean deviation between consecutive round trip times in milliseconds.
+    pub jitter_ms: f64,
+    /// Ratio of the last pings which were not answered, from 0 to 1.
+    pub packet_loss: f64,
+}
+
+/// Measures the connection of a remote from the pongs answering its pings.
+#[derive(Default)]
+pub struct LatencyTracker {
+    next_ping_id: u32,
+    pending_pings: HashMap<u32, Instant>,
+    last_rtt_ms: Option<f64>,
+    answered_pings: VecDeque<bool>,
+    stats: ConnectionStats,
+}
+
+impl LatencyTracker {
+    /// Returns the id of a new ping sent at `now`, the pings sent too long ago are counted as lost.
+    pub fn ping(&mut self, now: Instant) -> u32 {
+        let expired_pings: Vec<u32> = self
+            .pending_pings
+            .iter()
+            .filter(|(_, sent_at)| now.duration_since(**sent_at) > PING_TIMEOUT)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired_pings {
+            self.pending_pings.remove(&id);
+            self.record(false);
+        }
+
+        let id = self.next_ping_id;
+        self.next_ping_id = self.next_ping_id.wrapping_add(1);
+        self.pending_pings.insert(id, now);
+        id
+    }
+
+    /// Late or duplicated pongs are ignored.
+    pub fn pong(&mut self, id: u32, now: Instant) {
+        let Some(sent_at) = self.pending_pings.remove(&id) else {
+            return;
+        };
+        let rtt_ms = now.duration_since(sent_at).as_secs_f64() * 1000.;
+        // Same smoothing as the RTP jitter estimation
+        match self.last_rtt_ms {
+            Some(last_rtt_ms) => {
+                self.stats.rtt_ms += (rtt_ms - self.stats.rtt_ms) / 8.;
+                self.stats.jitter_ms += ((rtt_ms - last_rtt_ms).abs() - self.stats.jitter_ms) / 16.;
+            }
+            None => self.stats.rtt_ms = rtt_ms,
+        }
+        self.last_rtt_ms = Some(rtt_ms);
+        self.record(true);
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        self.stats
+    }
+
+    fn record(&mut self, answered: bool) {
+        if self.answered_pings.len() == LOSS_WINDOW {
+            self.answered_pings.pop_front();
+        }
+        self.answered_pings.push_back(answered);
+        let lost_pings = self
+            .answered_pings
+            .iter()
+            .filter(|answered| !**answered)
+            .count();
+        self.stats.packet_loss = lost_pings as f64 / self.answered_pings.len() as f64;
+    }
+}
+
+/// Connection statistics of the remotes by socket id, updated by the players connector.
+#[derive(Default)]
+pub struct NetworkStats {
+    stats: HashMap<u32, ConnectionStats>,
+}
+
+impl NetworkStats {
+    pub fn update(&mut self, socket_id: u32, stats: ConnectionStats) {
+        self.stats.insert(socket_id, stats);
+    }
+
+    pub fn remove(&mut self, socket_id: u32) {
+        self.stats.remove(&socket_id);
+    }
+
+    pub fn get(&self, socket_id: u32) -> Option<&ConnectionStats> {
+        self.stats.get(&socket_id)
+    }
+}
@@ -1,21 +1,85 @@
-use serde::Deserialize;
-use specs::Component;
-use specs::VecStorage;
-use specs_derive::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Debug)]
-#[storage(VecStorage)]
+use crate::{
+    components::{AbilityKind, Team},
+    game_modes::GameModeKind,
+    state::Phase,
+};
+
+#[derive(Debug)]
 pub struct PlayerInput {
     pub socket_id: u32,
     pub remote_input: RemoteInput,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
+/// Inputs of the remotes and of the local players received since the last frame, in their
+/// arrival order.
+#[derive(Default)]
+pub struct ReceivedInputs(Vec<PlayerInput>);
+
+impl ReceivedInputs {
+    pub fn push(&mut self, input: PlayerInput) {
+        self.0.push(input);
+    }
+
+    pub fn drain(&mut self) -> std::vec::Drain<'_, PlayerInput> {
+        self.0.drain(..)
+    }
+}
+
+/// Message from the game to the remote connected with the socket id.
+#[derive(Debug)]
+pub struct MessageToPlayer {
+    pub socket_id: u32,
+    pub message: MessageToRemote,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageToRemote {
+    /// Answer to the handshake of the remote, with the protocol version of the game and whether
+    /// it accepts the game inputs in the binary encoding.
+    #[serde(rename_all = "kebab-case")]
+    Handshake {
+        version: u32,
+        binary_inputs: bool,
+    },
+    PlayerState(PlayerState),
+    Feedback(Feedback),
+    /// Asks the remote to answer with a pong of the same id, to measure the connection.
+    Ping(u32),
+}
+
+/// Asks the remote to vibrate and flash its screen.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct Feedback {
+    /// Alternating vibration and pause durations in milliseconds.
+    pub vibration: Vec<u32>,
+    /// CSS color of the flash, no flash when `None`.
+    pub flash_color: Option<String>,
+}
+
+/// Personal state of a player, sent to their remote each time it changes.
+#[derive(Serialize, PartialEq, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct PlayerState {
+    pub phase: Phase,
+    pub is_ready: bool,
+    pub is_alive: bool,
+    pub extra_lives: u32,
+    pub rounds_in_magazine: u32,
+    pub reserve_ammo: u32,
+    pub is_reloading: bool,
+    /// Goes from 0 when the ability is used to 1 when it is ready again.
+    pub ability_progress: f64,
+    pub score: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
 pub enum RemoteInput {
     GameInput(GameInput),
     ConfigurationInput(ConfigurationInput),
-    NoInput,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -23,6 +87,8 @@ pub enum RemoteInput {
 pub enum GameInput {
     Aim(f64),
     Shoot,
+    Reload,
+    UseAbility,
     Stop,
     Move(f64),
 }
@@ -33,5 +99,9 @@ pub enum ConfigurationInput {
     Ready,
     NotReady,
     SetName(String),
+    SetTeam(Team),
+    LeaveTeam,
+    SetGameMode(GameModeKind),
+    SetAbility(AbilityKind),
     PlayerId(String),
 }
@@ -1,3 +1,7 @@
+mod latency;
 mod messages;
+mod protocol;
 
+pub use latency::*;
 pub use messages::*;
+pub use protocol::*;
@@ -0,0 +1,295 @@
+use serde::Deserialize;
+
+use crate::error::{Error, ErrorKind};
+
+use super::{ConfigurationInput, GameInput};
+
+/// Version of the protocol spoken between the game and the remotes, to be increased on every
+/// breaking change of the messages.
+pub const PROTOCOL_VERSION: u32 = 1;
+const MAX_NAME_LENGTH: usize = 20;
+const MAX_PLAYER_ID_LENGTH: usize = 64;
+
+/// Codes of the game inputs in the binary encoding.
+const STOP_CODE: u8 = 0;
+const SHOOT_CODE: u8 = 1;
+const RELOAD_CODE: u8 = 2;
+const USE_ABILITY_CODE: u8 = 3;
+const MOVE_CODE: u8 = 4;
+const AIM_CODE: u8 = 5;
+
+/// Every message from a remote is wrapped in an envelope with the protocol version, e.g.
+/// `{"version": 1, "sequence": 42, "message": {"type": "game", "input": {"move": 1.57}}}`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Envelope {
+    version: u32,
+    /// Increasing number given by the remote to its game inputs, so that the inputs overtaken
+    /// by a newer one on the unordered channel can be dropped.
+    #[serde(default)]
+    pub sequence: Option<u32>,
+    pub message: RemoteMessage,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", content = "input", rename_all = "kebab-case")]
+pub enum RemoteMessage {
+    /// First message of a remote, no input is accepted before it. Remotes without options only
+    /// speak JSON.
+    Handshake(Option<HandshakeOptions>),
+    Game(GameInput),
+    Configuration(ConfigurationInput),
+    /// Answer to the ping with the same id.
+    Pong(u32),
+}
+
+/// Options requested by the remote in its handshake.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct HandshakeOptions {
+    /// The remote sends its game inputs in the binary encoding instead of JSON.
+    #[serde(default)]
+    pub binary_inputs: bool,
+}
+
+impl Envelope {
+    /// Parses and validates a message from a remote.
+    pub fn parse(text: &str) -> Result<Envelope, Error> {
+        let envelope: Envelope = serde_json::from_str(text).map_err(|err| {
+            Error::from(err, ErrorKind::InvalidMessage).explain("malformed message")
+        })?;
+        if envelope.version != PROTOCOL_VERSION {
+            return Err(Error::new(
+                ErrorKind::UnsupportedProtocolVersion,
+                format!(
+                    "protocol version {} is not supported, expected {}",
+                    envelope.version, PROTOCOL_VERSION
+                ),
+            ));
+        }
+        envelope.message.validate()?;
+        Ok(envelope)
+    }
+
+    /// Parses and validates a game input in the binary encoding: one byte for the kind of input
+    /// followed, for `Move` and `Aim`, by the direction as a little-endian `f32` and optionally
+    /// by the sequence number as a little-endian `u32`.
+    pub fn parse_binary(data: &[u8]) -> Result<Envelope, Error> {
+        let read = |offset: usize| -> Option<[u8; 4]> {
+            data.get(offset..offset + 4)
+                .and_then(|bytes| bytes.try_into().ok())
+        };
+        let direction = || -> Result<f64, Error> {
+            let bytes = read(1)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidMessage, "the direction is missing"))?;
+            Ok(f32::from_le_bytes(bytes) as f64)
+        };
+        let input = match data.first() {
+            Some(&STOP_CODE) => GameInput::Stop,
+            Some(&SHOOT_CODE) => GameInput::Shoot,
+            Some(&RELOAD_CODE) => GameInput::Reload,
+            Some(&USE_ABILITY_CODE) => GameInput::UseAbility,
+            Some(&MOVE_CODE) => GameInput::Move(direction()?),
+            Some(&AIM_CODE) => GameInput::Aim(direction()?),
+            Some(code) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidMessage,
+                    format!("unknown binary input code {code}"),
+                ))
+            }
+            None => return Err(Error::new(ErrorKind::InvalidMessage, "empty message")),
+        };
+        let sequence_offset = match input {
+            GameInput::Move(_) | GameInput::Aim(_) => 5,
+            _ => 1,
+        };
+        let envelope = Envelope {
+            version: PROTOCOL_VERSION,
+            sequence: read(sequence_offset).map(u32::from_le_bytes),
+            message: RemoteMessage::Game(input),
+        };
+        envelope.message.validate()?;
+        Ok(envelope)
+    }
+}
+
+impl RemoteMessage {
+    fn validate(&self) -> Result<(), Error> {
+        match self {
+            RemoteMessage::Game(GameInput::Move(direction) | GameInput::Aim(direction))
+                if !direction.is_finite() =>
+            {
+                Err(Error::new(
+                    ErrorKind::InvalidMessage,
+                    "the direction is not a finite number",
+                ))
+            }
+            RemoteMessage::Configuration(ConfigurationInput::SetName(name))
+                if name.chars().count() > MAX_NAME_LENGTH =>
+            {
+                Err(Error::new(
+                    ErrorKind::InvalidMessage,
+                    format!("the name is longer than {MAX_NAME_LENGTH} characters"),
+                ))
+            }
+            RemoteMessage::Configuration(ConfigurationInput::PlayerId(id))
+                if id.is_empty() || id.len() > MAX_PLAYER_ID_LENGTH =>
+            {
+                Err(Error::new(
+                    ErrorKind::InvalidMessage,
+                    "the player id is empty or too long",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_message(input: &str) -> String {
+        format!(
+            r#"{{"version": {PROTOCOL_VERSION}, "message": {{"type": "game", "input": {input}}}}}"#
+        )
+    }
+
+    fn configuration_message(input: &str) -> String {
+        format!(
+            r#"{{"version": {PROTOCOL_VERSION}, "message": {{"type": "configuration", "input": {input}}}}}"#
+        )
+    }
+
+    fn binary_input(code: u8, direction: Option<f32>, sequence: Option<u32>) -> Vec<u8> {
+        let mut data = vec![code];
+        data.extend(direction.map(f32::to_le_bytes).into_iter().flatten());
+        data.extend(sequence.map(u32::to_le_bytes).into_iter().flatten());
+        data
+    }
+
+    #[test]
+    fn parses_a_valid_message() {
+        let text = format!(
+            r#"{{"version": {PROTOCOL_VERSION}, "sequence": 42, "message": {{"type": "game", "input": {{"move": 1.5}}}}}}"#
+        );
+
+        let envelope = Envelope::parse(&text).unwrap();
+
+        assert_eq!(envelope.sequence, Some(42));
+        assert!(
+            matches!(envelope.message, RemoteMessage::Game(GameInput::Move(direction)) if direction == 1.5),
+            "unexpected message {:?}",
+            envelope.message
+        );
+        assert!(Envelope::parse(&game_message(r#""shoot""#)).is_ok());
+        assert!(Envelope::parse(&configuration_message(r#"{"set-name": "Alice"}"#)).is_ok());
+        let handshake = format!(
+            r#"{{"version": {PROTOCOL_VERSION}, "message": {{"type": "handshake", "input": {{"binary-inputs": true}}}}}}"#
+        );
+        assert!(matches!(
+            Envelope::parse(&handshake).unwrap().message,
+            RemoteMessage::Handshake(Some(HandshakeOptions {
+                binary_inputs: true
+            }))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let message = game_message(r#"{"move": 1.5}"#);
+        let truncated = &message[..message.len() - 2];
+        assert!(Envelope::parse(truncated).is_err());
+        assert!(Envelope::parse("").is_err());
+        assert!(Envelope::parse(&game_message(r#""jump""#)).is_err());
+        assert!(Envelope::parse(&game_message(r#"{"move": NaN}"#)).is_err());
+        assert!(Envelope::parse(&game_message(r#"{"aim": 1e400}"#)).is_err());
+        let unknown_field = format!(
+            r#"{{"version": {PROTOCOL_VERSION}, "extra": true, "message": {{"type": "game", "input": "shoot"}}}}"#
+        );
+        assert!(Envelope::parse(&unknown_field).is_err());
+    }
+
+    #[test]
+    fn rejects_other_protocol_versions() {
+        let text = format!(
+            r#"{{"version": {}, "message": {{"type": "game", "input": "shoot"}}}}"#,
+            PROTOCOL_VERSION + 1
+        );
+
+        assert!(Envelope::parse(&text).is_err());
+    }
+
+    #[test]
+    fn limits_the_length_of_the_names_and_ids() {
+        let name = |length: usize| {
+            configuration_message(&format!(r#"{{"set-name": "{}"}}"#, "é".repeat(length)))
+        };
+        let id = |length: usize| {
+            configuration_message(&format!(r#"{{"player-id": "{}"}}"#, "a".repeat(length)))
+        };
+
+        assert!(Envelope::parse(&name(MAX_NAME_LENGTH)).is_ok());
+        assert!(Envelope::parse(&name(MAX_NAME_LENGTH + 1)).is_err());
+        assert!(Envelope::parse(&id(MAX_PLAYER_ID_LENGTH)).is_ok());
+        assert!(Envelope::parse(&id(MAX_PLAYER_ID_LENGTH + 1)).is_err());
+        assert!(Envelope::parse(&id(0)).is_err());
+    }
+
+    #[test]
+    fn parses_the_binary_inputs() {
+        for (code, expected) in [
+            (STOP_CODE, GameInput::Stop),
+            (SHOOT_CODE, GameInput::Shoot),
+            (RELOAD_CODE, GameInput::Reload),
+            (USE_ABILITY_CODE, GameInput::UseAbility),
+        ] {
+            let envelope = Envelope::parse_binary(&binary_input(code, None, None)).unwrap();
+            assert_eq!(
+                format!("{:?}", envelope.message),
+                format!("{:?}", RemoteMessage::Game(expected))
+            );
+            assert_eq!(envelope.sequence, None);
+        }
+
+        let envelope =
+            Envelope::parse_binary(&binary_input(MOVE_CODE, Some(1.5), Some(7))).unwrap();
+        assert!(matches!(
+            envelope.message,
+            RemoteMessage::Game(GameInput::Move(direction)) if direction == 1.5
+        ));
+        assert_eq!(envelope.sequence, Some(7));
+
+        let envelope = Envelope::parse_binary(&binary_input(AIM_CODE, Some(-0.5), None)).unwrap();
+        assert!(matches!(
+            envelope.message,
+            RemoteMessage::Game(GameInput::Aim(direction)) if direction == -0.5
+        ));
+        assert_eq!(envelope.sequence, None);
+
+        let envelope = Envelope::parse_binary(&binary_input(SHOOT_CODE, None, Some(3))).unwrap();
+        assert_eq!(envelope.sequence, Some(3));
+    }
+
+    #[test]
+    fn rejects_invalid_binary_inputs() {
+        assert!(Envelope::parse_binary(&[]).is_err());
+        assert!(Envelope::parse_binary(&[AIM_CODE + 1]).is_err());
+        assert!(Envelope::parse_binary(&[u8::MAX]).is_err());
+        assert!(Envelope::parse_binary(&[MOVE_CODE]).is_err());
+        assert!(Envelope::parse_binary(&binary_input(MOVE_CODE, Some(1.5), None)[..4]).is_err());
+        assert!(Envelope::parse_binary(&binary_input(AIM_CODE, Some(f32::NAN), None)).is_err());
+        assert!(
+            Envelope::parse_binary(&binary_input(MOVE_CODE, Some(f32::INFINITY), None)).is_err()
+        );
+    }
+
+    #[test]
+    fn ignores_a_truncated_sequence() {
+        let data = binary_input(MOVE_CODE, Some(1.5), Some(7));
+
+        let envelope = Envelope::parse_binary(&data[..data.len() - 1]).unwrap();
+
+        assert_eq!(envelope.sequence, None);
+    }
+}
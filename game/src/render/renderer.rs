@@ -5,13 +5,17 @@ use sdl2::{
     ttf::Font,
     video::Window,
 };
-use specs::{Entities, Join, ReadExpect, ReadStorage};
+use specs::{Entities, Join, Read, ReadExpect, ReadStorage};
 
 use crate::{
     components::*,
+    events::KillFeed,
+    game_modes::{ObjectiveKind, PlayerView},
+    remotes::{ConnectionStats, NetworkStats},
+    server_communicator::ServerStatus,
     state::{
         game_state::{Assets, Phase, State},
-        BlockKind, Map,
+        Block, BlockKind, Map, RoundTimer, DESTRUCTIBLE_WALL_HIT_POINTS,
     },
 };
 
@@ -24,6 +28,12 @@ type SystemDataType<'a> = (
     ReadStorage<'a, Player>,
     ReadStorage<'a, Bullet>,
     Entities<'a>,
+    ReadStorage<'a, Pickup>,
+    ReadStorage<'a, Mine>,
+    Read<'a, KillFeed>,
+    Read<'a, NetworkStats>,
+    ReadExpect<'a, Map>,
+    ReadExpect<'a, RoundTimer>,
 );
 
 pub struct SystemData<'a> {
@@ -59,6 +69,30 @@ impl<'a> SystemData<'a> {
     fn get_entities(&self) -> &Entities<'a> {
         &self.system_data.5
     }
+
+    fn get_pickups(&self) -> &ReadStorage<'a, Pickup> {
+        &self.system_data.6
+    }
+
+    fn get_mines(&self) -> &ReadStorage<'a, Mine> {
+        &self.system_data.7
+    }
+
+    fn get_kill_feed(&self) -> &Read<'a, KillFeed> {
+        &self.system_data.8
+    }
+
+    fn get_network_stats(&self) -> &Read<'a, NetworkStats> {
+        &self.system_data.9
+    }
+
+    fn get_map(&self) -> &ReadExpect<'a, Map> {
+        &self.system_data.10
+    }
+
+    fn get_round_timer(&self) -> &ReadExpect<'a, RoundTimer> {
+        &self.system_data.11
+    }
 }
 
 pub fn render(
@@ -93,6 +127,29 @@ fn render_before_game(assets: &mut Assets, data: SystemData, font: &Font, player
         canvas.fill_rect(square).unwrap();
     });
 
+    render_text(
+        canvas,
+        font,
+        &format_server_status(data.get_state().server_status),
+        Point::new(460, 60),
+    );
+
+    render_text(
+        canvas,
+        font,
+        &format!("Game mode: {}", data.get_state().game_mode.kind().name()),
+        Point::new(460, 100),
+    );
+
+    if let Some(winner) = &data.get_state().last_winner {
+        render_text(
+            canvas,
+            font,
+            &format!("{} won the last game!", winner),
+            Point::new(460, 140),
+        );
+    }
+
     let mut y = 200; // Calculate starting Y position
 
     for player in data.get_players().join() {
@@ -112,12 +169,18 @@ fn render_before_game(assets: &mut Assets, data: SystemData, font: &Font, player
         let face_dest_rect = Rect::new(460, y, 40, 40);
         canvas.copy(player_face, None, face_dest_rect).unwrap();
 
+        // Render the team of the player next to the face
+        if let Some(team) = player.team {
+            canvas.set_draw_color(get_team_color(team));
+            canvas.fill_rect(Rect::new(420, y, 40, 40)).unwrap();
+        }
+
         // Render player name next to the circle
         if !player.name.is_empty() {
             let texture_creator = canvas.texture_creator();
             let surface = font
                 .render(&player.name)
-                .blended(Color::RGBA(255, 0, 0, 255))
+                .blended(get_name_color(player))
                 .map_err(|e| e.to_string())
                 .unwrap();
             let texture = texture_creator
@@ -135,13 +198,37 @@ fn render_before_game(assets: &mut Assets, data: SystemData, font: &Font, player
                 )
                 .unwrap();
         }
+
+        // Render the latency of the player after the name
+        if let Some(stats) = data.get_network_stats().get(player.socket_id) {
+            let (name_width, _) = font.size_of(&player.name).unwrap();
+            render_text(
+                canvas,
+                font,
+                &format!("{:.0} ms", stats.rtt_ms),
+                Point::new(580 + name_width as i32, y + 10),
+            );
+        }
         // Increment the Y position for the next player
         y += 60;
     }
 
+    render_debug_overlay(&data, canvas, font);
     canvas.present();
 }
 
+fn format_server_status(status: ServerStatus) -> String {
+    match status {
+        ServerStatus::Connecting => "Connecting to the server...".to_owned(),
+        ServerStatus::Connected => "Connected to the server".to_owned(),
+        ServerStatus::Disconnected { retry_in } => format!(
+            "Disconnected from the server, retrying in {} s",
+            retry_in.as_secs()
+        ),
+        ServerStatus::LocalNetwork => "Playing on the local network".to_owned(),
+    }
+}
+
 fn render_game(
     assets: &mut Assets,
     data: SystemData,
@@ -154,12 +241,39 @@ fn render_game(
     canvas.set_draw_color(Color::RGB(173, 216, 150));
     canvas.clear();
 
-    let map = &data.get_state().map;
+    let map = data.get_map();
 
     render_map(map, canvas);
+    render_objectives(data.get_state(), canvas, map.block_size());
 
-    for (entity, position, circle) in
-        (data.get_entities(), data.get_position(), data.get_circle()).join()
+    for (position, circle, pickup) in
+        (data.get_position(), data.get_circle(), data.get_pickups()).join()
+    {
+        canvas.set_draw_color(get_pickup_color(pickup.kind));
+        canvas
+            .fill_rect(Rect::new(
+                ((position.x - circle.get_radius()) * map.block_size() as f64) as i32,
+                ((position.y - circle.get_radius()) * map.block_size() as f64) as i32,
+                (circle.get_size() * map.block_size() as f64) as u32,
+                (circle.get_size() * map.block_size() as f64) as u32,
+            ))
+            .unwrap();
+    }
+
+    for (position, circle, mine) in
+        (data.get_position(), data.get_circle(), data.get_mines()).join()
+    {
+        render_mine(canvas, mine, position, circle, map.block_size());
+    }
+
+    for (entity, position, circle, _, _) in (
+        data.get_entities(),
+        data.get_position(),
+        data.get_circle(),
+        !data.get_pickups(),
+        !data.get_mines(),
+    )
+        .join()
     {
         let player = data.get_players().get(entity);
         // Render the different circles: players + bullets
@@ -179,7 +293,13 @@ fn render_game(
 
         let mut alive_player = false;
         if let Some(player) = player {
-            alive_player = player.is_alive;
+            // Players standing in a bush or in their smoke are hidden
+            alive_player = player.is_alive
+                && !map.hides_tanks(&Block(position.x as u8, position.y as u8))
+                && !player.is_hidden_by_smoke();
+            if player.is_alive && player.is_hidden_by_smoke() {
+                render_smoke(canvas, position, map.block_size());
+            }
         }
 
         if alive_player || player.is_none() {
@@ -188,12 +308,19 @@ fn render_game(
 
         if alive_player {
             let player = player.unwrap();
+
+            // Surround the player with the team color
+            if let Some(team) = player.team {
+                canvas.set_draw_color(get_team_color(team));
+                canvas.draw_rect(face_dest_rect).unwrap();
+            }
+
             // Render player name next to the circle
             let texture_creator = canvas.texture_creator();
 
             let surface = font
                 .render(&player.name)
-                .blended(Color::RGBA(255, 0, 0, 255))
+                .blended(get_name_color(player))
                 .map_err(|e| e.to_string())
                 .unwrap();
             let texture = texture_creator
@@ -215,14 +342,177 @@ fn render_game(
                 )
                 .unwrap();
 
+            render_ammo(canvas, font, player, position, map.block_size());
+            render_effects(canvas, player, position, circle, map.block_size());
+
             // Render the player line when aiming
             render_aiming_line(canvas, position, &player.aim, map.block_size());
         }
     }
 
+    render_scores(&data, canvas, font);
+    render_sudden_death_timer(data.get_state(), data.get_round_timer(), canvas, font);
+    render_kill_feed(data.get_kill_feed(), canvas, font);
+    render_debug_overlay(&data, canvas, font);
+
     canvas.present();
 }
 
+fn render_text(canvas: &mut Canvas<Window>, font: &Font, text: &str, position: Point) {
+    let texture_creator = canvas.texture_creator();
+    let surface = font
+        .render(text)
+        .blended(Color::RGBA(0, 0, 0, 255))
+        .map_err(|e| e.to_string())
+        .unwrap();
+    let texture = texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())
+        .unwrap();
+    let font_rect = texture.query();
+
+    canvas
+        .copy(
+            &texture,
+            None,
+            Rect::new(position.x, position.y, font_rect.width, font_rect.height),
+        )
+        .unwrap();
+}
+
+/// Renders the scores of the game mode in the top left corner.
+fn render_scores(data: &SystemData, canvas: &mut Canvas<Window>, font: &Font) {
+    let player_views: Vec<PlayerView> = (data.get_players(), data.get_position().maybe())
+        .join()
+        .map(|(player, position)| PlayerView::new(player, position))
+        .collect();
+
+    let mut y = 10;
+    for score in data.get_state().game_mode.scores(&player_views) {
+        render_text(
+            canvas,
+            font,
+            &format!("{}: {}", score.name, score.points),
+            Point::new(10, y),
+        );
+        y += font.height();
+    }
+}
+
+/// Renders the last kills in the bottom left corner, the most recent one at the bottom.
+fn render_kill_feed(kill_feed: &KillFeed, canvas: &mut Canvas<Window>, font: &Font) {
+    let (_, height) = canvas.output_size().unwrap();
+    let mut y = height as i32 - 10 - kill_feed.entries().len() as i32 * font.height();
+    for entry in kill_feed.entries() {
+        render_text(canvas, font, &entry.text, Point::new(10, y));
+        y += font.height();
+    }
+}
+
+/// Renders the connection statistics of every player in the bottom right corner when the
+/// debug overlay is toggled.
+fn render_debug_overlay(data: &SystemData, canvas: &mut Canvas<Window>, font: &Font) {
+    if !data.get_state().show_debug_overlay {
+        return;
+    }
+
+    let lines: Vec<String> = data
+        .get_players()
+        .join()
+        .map(|player| {
+            let stats = data
+                .get_network_stats()
+                .get(player.socket_id)
+                .copied()
+                .unwrap_or_default();
+            format_connection_stats(&player.name, &stats)
+        })
+        .collect();
+    let (width, height) = canvas.output_size().unwrap();
+    let mut y = height as i32 - 10 - lines.len() as i32 * font.height();
+    for line in lines {
+        let (text_width, _) = font.size_of(&line).unwrap();
+        render_text(
+            canvas,
+            font,
+            &line,
+            Point::new(width as i32 - 10 - text_width as i32, y),
+        );
+        y += font.height();
+    }
+}
+
+fn format_connection_stats(name: &str, stats: &ConnectionStats) -> String {
+    format!(
+        "{}: {:.0} ms, jitter {:.0} ms, loss {:.0}%",
+        name,
+        stats.rtt_ms,
+        stats.jitter_ms,
+        stats.packet_loss * 100.
+    )
+}
+
+/// Renders the time left before the sudden death in the top right corner.
+fn render_sudden_death_timer(
+    state: &State,
+    round_timer: &RoundTimer,
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+) {
+    if let Some(sudden_death_after_seconds) = state.rules.sudden_death_after_seconds {
+        let seconds_in_round = round_timer.frames / FRAME_PER_SECOND;
+        let text = match sudden_death_after_seconds.checked_sub(seconds_in_round) {
+            Some(seconds_left) if seconds_left > 0 => format!(
+                "Sudden death in {}:{:02}",
+                seconds_left / 60,
+                seconds_left % 60
+            ),
+            _ => "Sudden death!".to_owned(),
+        };
+        let (width, _) = canvas.output_size().unwrap();
+        let (text_width, _) = font.size_of(&text).unwrap();
+        render_text(
+            canvas,
+            font,
+            &text,
+            Point::new(width as i32 - text_width as i32 - 10, 10),
+        );
+    }
+}
+
+fn render_objectives(state: &State, canvas: &mut Canvas<Window>, map_block_size: u32) {
+    for objective in state.game_mode.objectives() {
+        let x = (objective.position.x * map_block_size as f64) as i32;
+        let y = (objective.position.y * map_block_size as f64) as i32;
+
+        match objective.kind {
+            ObjectiveKind::Flag(team) => {
+                let flag_size = map_block_size / 4;
+                // Draw the pole then the flag of the team
+                canvas.set_draw_color(Color::RGB(0, 0, 0));
+                canvas
+                    .draw_line((x, y), (x, y - 2 * flag_size as i32))
+                    .unwrap();
+                canvas.set_draw_color(get_team_color(team));
+                canvas
+                    .fill_rect(Rect::new(x, y - 2 * flag_size as i32, flag_size, flag_size))
+                    .unwrap();
+            }
+            ObjectiveKind::Hill => {
+                canvas.set_draw_color(Color::RGB(255, 215, 0));
+                canvas
+                    .draw_rect(Rect::new(
+                        x - map_block_size as i32 / 2,
+                        y - map_block_size as i32 / 2,
+                        map_block_size,
+                        map_block_size,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
+
 fn render_map(map: &Map, canvas: &mut Canvas<Window>) {
     let block_size = map.block_size();
     canvas.set_draw_color(Color::RGB(255, 255, 255));
@@ -248,11 +538,170 @@ fn render_map(map: &Map, canvas: &mut Canvas<Window>) {
                         .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
                         .unwrap();
                 }
+                BlockKind::DestructibleWall(hit_points) => {
+                    // Draw a destructible wall block, lighter when damaged
+                    let damage = (DESTRUCTIBLE_WALL_HIT_POINTS - hit_points) as u8;
+                    canvas.set_draw_color(Color::RGB(139, 69 + damage * 30, 19 + damage * 30));
+                    canvas
+                        .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
+                        .unwrap();
+                }
+                BlockKind::Water => {
+                    // Draw a water block
+                    canvas.set_draw_color(Color::RGB(64, 164, 223));
+                    canvas
+                        .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
+                        .unwrap();
+                }
+                BlockKind::Bush => {
+                    // Draw a bush block
+                    canvas.set_draw_color(Color::RGB(34, 139, 34));
+                    canvas
+                        .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
+                        .unwrap();
+                }
+                BlockKind::Hazard => {
+                    // Draw a block closed by the sudden death
+                    canvas.set_draw_color(Color::RGB(255, 69, 0));
+                    canvas
+                        .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
+                        .unwrap();
+                }
+                BlockKind::Hole => {
+                    // Draw a hole block as a dark square surrounded by ground
+                    canvas.set_draw_color(Color::RGB(255, 255, 255));
+                    canvas
+                        .fill_rect(Rect::new(block_x, block_y, block_size, block_size))
+                        .unwrap();
+                    canvas.set_draw_color(Color::RGB(40, 40, 40));
+                    canvas
+                        .fill_rect(Rect::new(
+                            block_x + block_size as i32 / 8,
+                            block_y + block_size as i32 / 8,
+                            block_size * 3 / 4,
+                            block_size * 3 / 4,
+                        ))
+                        .unwrap();
+                }
             }
         }
     }
 }
 
+fn render_ammo(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    player: &Player,
+    position: &Position,
+    map_block_size: u32,
+) {
+    let ammo_text = if player.is_reloading() {
+        "reloading...".to_owned()
+    } else {
+        format!("{} / {}", player.ammo.in_magazine, player.ammo.reserve)
+    };
+
+    // Render the ammo below the player name
+    render_text(
+        canvas,
+        font,
+        &ammo_text,
+        Point::new(
+            (position.x * map_block_size as f64) as i32,
+            (position.y * map_block_size as f64) as i32 + font.height(),
+        ),
+    );
+}
+
+/// Renders a small square above the player for each active effect and extra life.
+fn render_effects(
+    canvas: &mut Canvas<Window>,
+    player: &Player,
+    position: &Position,
+    circle: &Circle,
+    map_block_size: u32,
+) {
+    let square_size = map_block_size / 10;
+    let effect_colors = player
+        .effects
+        .iter()
+        .map(|effect| get_pickup_color(effect.kind))
+        .chain((0..player.extra_lives).map(|_| get_pickup_color(PickupKind::ExtraLife)));
+
+    let start_x = ((position.x - circle.get_radius()) * map_block_size as f64) as i32;
+    let start_y = ((position.y - circle.get_radius()) * map_block_size as f64) as i32;
+
+    for (index, color) in effect_colors.enumerate() {
+        canvas.set_draw_color(color);
+        canvas
+            .fill_rect(Rect::new(
+                start_x + index as i32 * (square_size as i32 + 2),
+                start_y - square_size as i32 - 2,
+                square_size,
+                square_size,
+            ))
+            .unwrap();
+    }
+}
+
+/// Renders a mine as a dark square inside its trigger area, in the owner team color if any.
+fn render_mine(
+    canvas: &mut Canvas<Window>,
+    mine: &Mine,
+    position: &Position,
+    circle: &Circle,
+    map_block_size: u32,
+) {
+    let mine_size = (circle.get_radius() * map_block_size as f64) as u32;
+    canvas.set_draw_color(
+        mine.owner_team
+            .map_or(Color::RGB(60, 60, 60), get_team_color),
+    );
+    canvas
+        .fill_rect(Rect::new(
+            (position.x * map_block_size as f64) as i32 - mine_size as i32 / 2,
+            (position.y * map_block_size as f64) as i32 - mine_size as i32 / 2,
+            mine_size,
+            mine_size,
+        ))
+        .unwrap();
+}
+
+fn render_smoke(canvas: &mut Canvas<Window>, position: &Position, map_block_size: u32) {
+    canvas.set_draw_color(Color::RGB(169, 169, 169));
+    canvas
+        .fill_rect(Rect::new(
+            (position.x * map_block_size as f64) as i32 - map_block_size as i32 / 2,
+            (position.y * map_block_size as f64) as i32 - map_block_size as i32 / 2,
+            map_block_size,
+            map_block_size,
+        ))
+        .unwrap();
+}
+
+fn get_team_color(team: Team) -> Color {
+    match team {
+        Team::Red => Color::RGB(220, 20, 60),
+        Team::Blue => Color::RGB(30, 144, 255),
+    }
+}
+
+fn get_name_color(player: &Player) -> Color {
+    player
+        .team
+        .map_or(Color::RGBA(255, 0, 0, 255), get_team_color)
+}
+
+fn get_pickup_color(kind: PickupKind) -> Color {
+    match kind {
+        PickupKind::SpeedBoost => Color::RGB(255, 215, 0),
+        PickupKind::Shield => Color::RGB(30, 144, 255),
+        PickupKind::RapidFire => Color::RGB(220, 20, 60),
+        PickupKind::ExtraLife => Color::RGB(50, 205, 50),
+        PickupKind::BouncingAmmo => Color::RGB(148, 0, 211),
+    }
+}
+
 fn render_aiming_line(
     canvas: &mut Canvas<Window>,
     position: &Position,
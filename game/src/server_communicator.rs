@@ -1,10 +1,20 @@
+use std::time::{Duration, Instant};
+
 use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
-use futures_util::{future, pin_mut, StreamExt};
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::debug;
+use tokio::{
+    net::TcpStream,
+    select,
+    time::{interval, sleep},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
 
-use crate::game::{MessageToGame, RoomId};
+use crate::{
+    configuration::IceServerSettings,
+    game::{MessageToGame, RoomId},
+};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -12,6 +22,9 @@ use crate::game::{MessageToGame, RoomId};
 pub enum ServerMessage {
     RoomId(String),
     SdpOffer(SdpMessage),
+    IceCandidate(IceCandidateMessage),
+    IceServers(IceServersMessage),
+    ReclaimToken(ReclaimTokenMessage),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -21,64 +34,248 @@ pub struct SdpMessage {
     pub data: String,
 }
 
+/// ICE candidate of the peer connection started by the offer with the same id. The candidates
+/// are trickled after the offer and the answer, so the connection starts before the gathering
+/// of the candidates is complete.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct IceCandidateMessage {
+    pub id: u32,
+    /// JSON of the candidate, `None` once every candidate was sent.
+    pub candidate: Option<String>,
+}
+
+/// Sent instead of the answer when the offer with the same id is invalid.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct SdpErrorMessage {
+    pub id: u32,
+    pub error: String,
+}
+
+/// STUN and TURN servers provided by the server when the room is registered.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct IceServersMessage {
+    pub ice_servers: Vec<IceServerSettings>,
+}
+
+/// Secret given by the server with the room id, which lets the game get the same room back
+/// when it reconnects.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReclaimTokenMessage {
+    pub reclaim_token: String,
+}
+
 #[derive(Serialize, Debug)]
 #[serde(untagged)]
 pub enum MessageToServer {
     SdpAnswer(SdpMessage),
+    SdpError(SdpErrorMessage),
+    IceCandidate(IceCandidateMessage),
+}
+
+/// Signaling messages of the remotes, forwarded to the players connector.
+#[derive(Debug)]
+pub enum SignalingMessage {
+    Offer(SdpMessage),
+    IceCandidate(IceCandidateMessage),
+    IceServers(Vec<IceServerSettings>),
+}
+
+/// First delay before reconnecting to the server, doubled after each failed attempt.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Interval between two pings sent to the server to keep the connection alive.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+/// The connection is considered dead when nothing is received from the server for this long.
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connection of the game with the server, shown in the lobby.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ServerStatus {
+    Connecting,
+    Connected,
+    /// The connection was lost or could not be established, the next attempt starts after
+    /// `retry_in`.
+    Disconnected {
+        retry_in: Duration,
+    },
+    /// The game serves the signaling itself on the local network, without the server.
+    LocalNetwork,
+}
+
+/// Room registered on the server, reclaimed with its token after a reconnection.
+struct Room {
+    id: String,
+    reclaim_token: Option<String>,
 }
 
 pub struct ServerCommunicator {
     sender_to_game: UnboundedSender<MessageToGame>,
-    sender_to_player_connector: UnboundedSender<SdpMessage>,
+    sender_to_player_connector: UnboundedSender<SignalingMessage>,
     url: String,
+    room: Option<Room>,
 }
 
 impl ServerCommunicator {
     pub fn new<S: Into<String>>(
         sender_to_game: UnboundedSender<MessageToGame>,
-        sender_to_player_connector: UnboundedSender<SdpMessage>,
+        sender_to_player_connector: UnboundedSender<SignalingMessage>,
         url: S,
     ) -> Self {
         Self {
             sender_to_game,
             sender_to_player_connector,
             url: url.into(),
+            room: None,
         }
     }
 
-    pub async fn start(self, receiver: UnboundedReceiver<MessageToServer>) {
-        let (ws_stream, _) = connect_async(&self.url).await.expect("Failed to connect");
-
-        debug!("WebSocket handshake has been successfully completed.");
-
-        let (write, read) = ws_stream.split();
-
-        let handle_server_messages = read.for_each(|message| async {
-            debug!("Received message from server: {:?}.", message);
-            let message = message.unwrap();
-            let message: ServerMessage = serde_json::from_str(&message.to_text().unwrap()).unwrap();
-            match message {
-                ServerMessage::SdpOffer(sdp_offer) => self
-                    .sender_to_player_connector
-                    .clone()
-                    .start_send(sdp_offer)
-                    .unwrap(),
-                ServerMessage::RoomId(room_id) => self
-                    .sender_to_game
-                    .clone()
-                    .start_send(MessageToGame::RoomId(RoomId(room_id)))
-                    .unwrap(),
+    /// Keeps the game connected to the server, reconnecting with an exponential backoff, until
+    /// the players connector stops.
+    pub async fn start(mut self, mut receiver: UnboundedReceiver<MessageToServer>) {
+        let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+        loop {
+            self.send_status(ServerStatus::Connecting);
+            match connect_async(self.connection_url()).await {
+                Ok((ws_stream, _)) => {
+                    info!("Connected to the server.");
+                    reconnect_delay = INITIAL_RECONNECT_DELAY;
+                    self.send_status(ServerStatus::Connected);
+                    if !self.communicate(ws_stream, &mut receiver).await {
+                        return;
+                    }
+                    warn!("Lost the connection with the server.");
+                }
+                Err(err) => warn!("Failed to connect to the server: {}.", err),
             }
-        });
 
-        let handle_answer = receiver
-            .map(|answer| {
-                debug!("Received answer from server_communicator.");
-                Ok(Message::text(serde_json::to_string(&answer).unwrap()))
-            })
-            .forward(write);
+            self.send_status(ServerStatus::Disconnected {
+                retry_in: reconnect_delay,
+            });
+            sleep(reconnect_delay).await;
+            reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// URL of the server, with the room to reclaim when the game was already registered.
+    fn connection_url(&self) -> String {
+        match &self.room {
+            Some(Room {
+                id,
+                reclaim_token: Some(reclaim_token),
+            }) => format!(
+                "{}?room-id={}&reclaim-token={}",
+                self.url, id, reclaim_token
+            ),
+            _ => self.url.clone(),
+        }
+    }
+
+    /// Exchanges the signaling messages with the server until the connection is lost. Returns
+    /// `false` when the players connector stopped, so there is nothing left to do.
+    async fn communicate(
+        &mut self,
+        ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        receiver: &mut UnboundedReceiver<MessageToServer>,
+    ) -> bool {
+        let (mut write, mut read) = ws_stream.split();
+        let mut keepalive = interval(KEEPALIVE_INTERVAL);
+        let mut last_received = Instant::now();
+        loop {
+            select! {
+                message = read.next() => match message {
+                    Some(Ok(message)) => {
+                        last_received = Instant::now();
+                        self.handle_server_message(message);
+                    }
+                    Some(Err(err)) => {
+                        debug!("Failed to read a message from the server: {}.", err);
+                        return true;
+                    }
+                    None => return true,
+                },
+                message = receiver.next() => {
+                    let Some(message) = message else {
+                        return false;
+                    };
+                    debug!("Received signaling message from server_communicator.");
+                    let message = Message::text(serde_json::to_string(&message).unwrap());
+                    if let Err(err) = write.send(message).await {
+                        debug!("Failed to send a message to the server: {}.", err);
+                        return true;
+                    }
+                }
+                _ = keepalive.tick() => {
+                    if last_received.elapsed() > KEEPALIVE_TIMEOUT {
+                        debug!("The server did not answer the keepalive pings.");
+                        return true;
+                    }
+                    if let Err(err) = write.send(Message::Ping(Default::default())).await {
+                        debug!("Failed to send a keepalive ping to the server: {}.", err);
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_server_message(&mut self, message: Message) {
+        debug!("Received message from server: {:?}.", message);
+        // The pings and pongs only keep the connection alive
+        let Message::Text(text) = message else {
+            return;
+        };
+        let message = match serde_json::from_str::<ServerMessage>(&text) {
+            Ok(message) => message,
+            Err(err) => {
+                warn!("Ignored an invalid message from the server: {}.", err);
+                return;
+            }
+        };
+        match message {
+            ServerMessage::SdpOffer(sdp_offer) => {
+                self.send_to_player_connector(SignalingMessage::Offer(sdp_offer))
+            }
+            ServerMessage::IceCandidate(candidate) => {
+                self.send_to_player_connector(SignalingMessage::IceCandidate(candidate))
+            }
+            ServerMessage::IceServers(message) => {
+                self.send_to_player_connector(SignalingMessage::IceServers(message.ice_servers))
+            }
+            ServerMessage::RoomId(room_id) => {
+                let is_reclaimed = self.room.as_ref().is_some_and(|room| room.id == room_id);
+                if !is_reclaimed {
+                    self.room = Some(Room {
+                        id: room_id.clone(),
+                        reclaim_token: None,
+                    });
+                    self.send_to_game(MessageToGame::RoomId(RoomId(room_id)));
+                }
+            }
+            ServerMessage::ReclaimToken(message) => {
+                if let Some(room) = &mut self.room {
+                    room.reclaim_token = Some(message.reclaim_token);
+                }
+            }
+        }
+    }
 
-        pin_mut!(handle_server_messages, handle_answer);
-        future::select(handle_server_messages, handle_answer).await;
+    fn send_status(&self, status: ServerStatus) {
+        self.send_to_game(MessageToGame::ServerStatus(status));
+    }
+
+    fn send_to_game(&self, message: MessageToGame) {
+        if let Err(err) = self.sender_to_game.unbounded_send(message) {
+            debug!("failed to send message {}", err.to_string());
+        }
+    }
+
+    fn send_to_player_connector(&self, message: SignalingMessage) {
+        if let Err(err) = self.sender_to_player_connector.unbounded_send(message) {
+            debug!("failed to send message {}", err.to_string());
+        }
     }
 }
@@ -0,0 +1,184 @@
+use specs::Entity;
+
+use crate::components::Position;
+
+/// Entity registered in the grid with its position at the time the grid was built.
+pub struct GridEntry {
+    pub entity: Entity,
+    pub position: Position,
+    pub radius: f64,
+}
+
+impl GridEntry {
+    pub fn overlaps(&self, position: &Position, radius: f64) -> bool {
+        let distance = ((self.position.x - position.x).powi(2)
+            + (self.position.y - position.y).powi(2))
+        .sqrt();
+        distance < self.radius + radius
+    }
+}
+
+/// Uniform grid of one block cells used as a broadphase for the circle collisions.
+/// It is rebuilt every frame, a query only returns the entities sharing a cell with the
+/// queried area instead of every entity of the map.
+pub struct CollisionGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<GridEntry>>,
+}
+
+impl CollisionGrid {
+    pub fn new(width: u8, height: u8) -> CollisionGrid {
+        let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+        CollisionGrid {
+            width,
+            height,
+            cells: (0..width * height).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    /// Registers the entity in every cell overlapped by its bounding box.
+    pub fn insert(&mut self, entity: Entity, position: &Position, radius: f64) {
+        for index in self.cell_indexes(position, radius) {
+            self.cells[index].push(GridEntry {
+                entity,
+                position: position.clone(),
+                radius,
+            });
+        }
+    }
+
+    /// Returns the entities which may collide with the circle, each entity is returned once.
+    pub fn query(&self, position: &Position, radius: f64) -> Vec<&GridEntry> {
+        let mut entries: Vec<&GridEntry> = self
+            .cell_indexes(position, radius)
+            .flat_map(|index| self.cells[index].iter())
+            .collect();
+        entries.sort_unstable_by_key(|entry| entry.entity);
+        entries.dedup_by_key(|entry| entry.entity);
+        entries
+    }
+
+    /// Entities outside of the map are kept in the cells of the closest border.
+    fn cell_indexes(&self, position: &Position, radius: f64) -> impl Iterator<Item = usize> {
+        let clamp = |value: f64, size: usize| (value.max(0.) as usize).min(size - 1);
+        let (min_x, max_x) = (
+            clamp(position.x - radius, self.width),
+            clamp(position.x + radius, self.width),
+        );
+        let (min_y, max_y) = (
+            clamp(position.y - radius, self.height),
+            clamp(position.y + radius, self.height),
+        );
+        let width = self.width;
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| y * width + x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, World, WorldExt};
+
+    use super::*;
+
+    fn create_entities(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.create_entity().build()).collect()
+    }
+
+    fn position(x: f64, y: f64) -> Position {
+        Position { x, y }
+    }
+
+    fn queried_entities(grid: &CollisionGrid, position: &Position, radius: f64) -> Vec<Entity> {
+        grid.query(position, radius)
+            .iter()
+            .map(|entry| entry.entity)
+            .collect()
+    }
+
+    #[test]
+    fn query_returns_the_entities_of_the_overlapped_cells() {
+        let entities = create_entities(2);
+        let mut grid = CollisionGrid::new(16, 9);
+        grid.insert(entities[0], &position(1.5, 1.5), 0.25);
+        grid.insert(entities[1], &position(10.5, 5.5), 0.25);
+
+        assert_eq!(
+            queried_entities(&grid, &position(1.6, 1.4), 0.25),
+            vec![entities[0]]
+        );
+        assert_eq!(
+            queried_entities(&grid, &position(10.2, 5.8), 0.25),
+            vec![entities[1]]
+        );
+        assert!(queried_entities(&grid, &position(5.5, 5.5), 0.25).is_empty());
+    }
+
+    #[test]
+    fn query_returns_an_entity_over_several_cells_once() {
+        let entities = create_entities(1);
+        let mut grid = CollisionGrid::new(16, 9);
+        grid.insert(entities[0], &position(3., 3.), 0.5);
+
+        assert_eq!(
+            queried_entities(&grid, &position(3., 3.), 0.5),
+            vec![entities[0]]
+        );
+        assert_eq!(
+            queried_entities(&grid, &position(2.2, 2.2), 0.1),
+            vec![entities[0]]
+        );
+        assert_eq!(
+            queried_entities(&grid, &position(3.8, 3.8), 0.1),
+            vec![entities[0]]
+        );
+    }
+
+    #[test]
+    fn entities_outside_of_the_map_are_in_the_border_cells() {
+        let entities = create_entities(2);
+        let mut grid = CollisionGrid::new(16, 9);
+        grid.insert(entities[0], &position(-2., -2.), 0.25);
+        grid.insert(entities[1], &position(20., 12.), 0.25);
+
+        assert_eq!(
+            queried_entities(&grid, &position(0.5, 0.5), 0.1),
+            vec![entities[0]]
+        );
+        assert_eq!(
+            queried_entities(&grid, &position(15.5, 8.5), 0.1),
+            vec![entities[1]]
+        );
+    }
+
+    #[test]
+    fn clear_removes_every_entity() {
+        let entities = create_entities(1);
+        let mut grid = CollisionGrid::new(16, 9);
+        grid.insert(entities[0], &position(1.5, 1.5), 0.25);
+
+        grid.clear();
+
+        assert!(queried_entities(&grid, &position(1.5, 1.5), 0.25).is_empty());
+    }
+
+    #[test]
+    fn entries_overlap_the_circles_closer_than_their_radiuses() {
+        let entities = create_entities(1);
+        let entry = GridEntry {
+            entity: entities[0],
+            position: position(1., 1.),
+            radius: 0.25,
+        };
+
+        assert!(entry.overlaps(&position(1.4, 1.), 0.2));
+        assert!(!entry.overlaps(&position(1.5, 1.), 0.2));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::remotes::Feedback;
+
+/// Feedback sent to the remote of a player for the game events concerning them.
+pub struct FeedbackPatterns {
+    pub hit: Feedback,
+    pub shot: Feedback,
+    pub death: Feedback,
+}
+
+impl Default for FeedbackPatterns {
+    fn default() -> Self {
+        FeedbackPatterns {
+            hit: Feedback {
+                vibration: vec![100],
+                flash_color: Some("#ff0000".to_owned()),
+            },
+            shot: Feedback {
+                vibration: vec![20],
+                flash_color: None,
+            },
+            death: Feedback {
+                vibration: vec![300, 100, 300],
+                flash_color: Some("#000000".to_owned()),
+            },
+        }
+    }
+}
@@ -1,15 +1,21 @@
 use sdl2::{render::Canvas, video::Window, Sdl};
+use serde::Serialize;
 
-use crate::room_code::RoomCode;
+use crate::{game_modes::GameMode, room_code::RoomCode, server_communicator::ServerStatus};
 
-use super::Map;
+use super::Rules;
 
 pub struct State {
     pub room_code: RoomCode,
     pub phase: Phase,
     pub number_of_ready_players: u32,
-    pub map: Map,
     pub resolution: (u32, u32),
+    pub rules: Rules,
+    pub last_winner: Option<String>,
+    pub game_mode: Box<dyn GameMode>,
+    /// Toggled with F3, shows the connection statistics of the remotes.
+    pub show_debug_overlay: bool,
+    pub server_status: ServerStatus,
 }
 
 pub struct Assets {
@@ -17,6 +23,8 @@ pub struct Assets {
     pub sdl_context: Sdl,
 }
 
+#[derive(Serialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
 pub enum Phase {
     BeforeNextGame,
     InGame,
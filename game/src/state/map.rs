@@ -1,31 +1,92 @@
-use rand::Rng;
+use rand::seq::SliceRandom;
 use std::{
-    collections::HashSet,
     fs::File,
     io::{BufRead, BufReader},
 };
 
-use crate::error::{Error, ErrorKind::*};
+use crate::{
+    components::{Position, Team},
+    error::{Error, ErrorKind::*},
+};
 
 const PLAYER_DIAMETER: f32 = 0.5;
+pub const DESTRUCTIBLE_WALL_HIT_POINTS: u32 = 3;
+const WATER_SPEED_FACTOR: f64 = 0.5;
+/// Smallest map, the screen shows at least this number of blocks.
+const MIN_DIMENSIONS: Dimensions = Dimensions(16, 9);
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Block(pub u8, pub u8);
+
+impl Block {
+    pub fn center(&self) -> Position {
+        Position {
+            x: 0.5 + self.0 as f64,
+            y: 0.5 + self.1 as f64,
+        }
+    }
+}
 #[derive(Clone, Copy)]
 struct Dimensions(pub u8, pub u8);
 
+/// Spawn block, reserved to a team when the team is set.
+#[derive(Clone, Copy)]
+struct Spawn {
+    block: Block,
+    team: Option<Team>,
+}
+
 pub struct Map {
     dimensions: Dimensions,
-    wall_set: HashSet<Block>,
-    spawns: Vec<Block>,
-    available_spawns: Vec<Block>,
+    spawns: Vec<Spawn>,
+    available_spawns: Vec<Spawn>,
+    pickup_spawns: Vec<Block>,
+    hill_blocks: Vec<Block>,
+    ground_blocks: Vec<Block>,
     pub block_matrix: Vec<Vec<BlockKind>>,
+    initial_block_matrix: Vec<Vec<BlockKind>>,
     block_size: u32,
 }
 
+#[derive(Clone)]
 pub enum BlockKind {
     Wall,
     Ground,
+    /// Wall destroyed after receiving its remaining hit points in bullets.
+    DestructibleWall(u32),
+    Water,
+    Bush,
+    Hole,
+    /// Ground closed by the sudden death, damaging the tanks standing on it.
+    Hazard,
+}
+
+impl BlockKind {
+    pub fn blocks_tanks(&self) -> bool {
+        matches!(
+            self,
+            BlockKind::Wall | BlockKind::DestructibleWall(_) | BlockKind::Hole
+        )
+    }
+
+    pub fn blocks_bullets(&self) -> bool {
+        matches!(self, BlockKind::Wall | BlockKind::DestructibleWall(_))
+    }
+
+    pub fn hides_tanks(&self) -> bool {
+        matches!(self, BlockKind::Bush)
+    }
+
+    pub fn is_hazard(&self) -> bool {
+        matches!(self, BlockKind::Hazard)
+    }
+
+    pub fn speed_factor(&self) -> f64 {
+        match self {
+            BlockKind::Water => WATER_SPEED_FACTOR,
+            _ => 1.,
+        }
+    }
 }
 
 impl Map {
@@ -35,8 +96,10 @@ impl Map {
         })?;
         let reader = BufReader::new(file);
 
-        let mut wall_set = HashSet::new();
         let mut spawns = Vec::new();
+        let mut pickup_spawns = Vec::new();
+        let mut hill_blocks = Vec::new();
+        let mut ground_blocks = Vec::new();
         let mut block_matrix = Vec::new();
         let mut y = 0;
 
@@ -50,14 +113,52 @@ impl Map {
             for (x, ch) in chars.iter().enumerate() {
                 match ch {
                     'x' => {
-                        wall_set.insert(Block(x as u8, y as u8));
                         block_row.push(BlockKind::Wall);
                     }
+                    'd' => {
+                        block_row.push(BlockKind::DestructibleWall(DESTRUCTIBLE_WALL_HIT_POINTS));
+                    }
+                    'w' => {
+                        block_row.push(BlockKind::Water);
+                    }
+                    'b' => {
+                        block_row.push(BlockKind::Bush);
+                    }
+                    'o' => {
+                        block_row.push(BlockKind::Hole);
+                    }
                     's' => {
-                        spawns.push(Block(x as u8, y as u8));
+                        spawns.push(Spawn {
+                            block: Block(x as u8, y as u8),
+                            team: None,
+                        });
+                        block_row.push(BlockKind::Ground);
+                    }
+                    '1' => {
+                        spawns.push(Spawn {
+                            block: Block(x as u8, y as u8),
+                            team: Some(Team::Red),
+                        });
+                        block_row.push(BlockKind::Ground);
+                    }
+                    '2' => {
+                        spawns.push(Spawn {
+                            block: Block(x as u8, y as u8),
+                            team: Some(Team::Blue),
+                        });
+                        block_row.push(BlockKind::Ground);
+                    }
+                    'h' => {
+                        hill_blocks.push(Block(x as u8, y as u8));
+                        block_row.push(BlockKind::Ground);
+                    }
+                    'p' => {
+                        pickup_spawns.push(Block(x as u8, y as u8));
+                        ground_blocks.push(Block(x as u8, y as u8));
                         block_row.push(BlockKind::Ground);
                     }
                     _ => {
+                        ground_blocks.push(Block(x as u8, y as u8));
                         block_row.push(BlockKind::Ground);
                     }
                 }
@@ -67,35 +168,206 @@ impl Map {
             y += 1;
         }
 
+        // The missing blocks at the end of the short rows and below the last row are ground
+        let width = block_matrix
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+            .max(MIN_DIMENSIONS.0 as usize);
+        let height = block_matrix.len().max(MIN_DIMENSIONS.1 as usize);
+        block_matrix.resize_with(height, Vec::new);
+        for (y, row) in block_matrix.iter_mut().enumerate() {
+            for x in row.len()..width {
+                ground_blocks.push(Block(x as u8, y as u8));
+                row.push(BlockKind::Ground);
+            }
+        }
+
         let available_spawns = spawns.clone();
 
-        let dimension = Dimensions(16, 9);
-        let block_size = resolution.0 / dimension.0 as u32;
+        let dimensions = Dimensions(width as u8, height as u8);
+        let block_size =
+            (resolution.0 / dimensions.0 as u32).min(resolution.1 / dimensions.1 as u32);
 
         Ok(Map {
-            dimensions: Dimensions(16, 9),
-            wall_set,
+            dimensions,
             spawns,
+            initial_block_matrix: block_matrix.clone(),
             block_matrix,
             available_spawns,
+            pickup_spawns,
+            hill_blocks,
+            ground_blocks,
             block_size,
         })
     }
 
-    pub fn get_spawn_block(&mut self) -> Result<Block, Error> {
-        let range = 0..self.available_spawns.len();
-        if range.is_empty() {
-            return Err(Error::new(NoSpawnAvailable, "no more spawns available"));
+    /// Takes a random spawn, players in a team use their team spawns when the map has some.
+    pub fn get_spawn_block(&mut self, team: Option<Team>) -> Result<Block, Error> {
+        let index = Self::choose_spawn(&self.available_spawns, team)?;
+        Ok(self.available_spawns.remove(index).block)
+    }
+
+    /// Picks a random spawn during the game, spawns are not taken so several players can use
+    /// the same one. The spawns closed by the sudden death are skipped, once they are all
+    /// closed the players respawn on a random open block.
+    pub fn get_respawn_block(&self, team: Option<Team>) -> Result<Block, Error> {
+        let open_spawns: Vec<Spawn> = self
+            .spawns
+            .iter()
+            .filter(|spawn| !self.is_hazard(&spawn.block))
+            .copied()
+            .collect();
+        match Self::choose_spawn(&open_spawns, team) {
+            Ok(index) => Ok(open_spawns[index].block),
+            Err(err) => {
+                let open_blocks: Vec<&Block> = self
+                    .ground_blocks
+                    .iter()
+                    .filter(|block| !self.is_hazard(block))
+                    .collect();
+                open_blocks
+                    .choose(&mut rand::thread_rng())
+                    .map(|block| **block)
+                    .ok_or(err)
+            }
         }
-        let random_index = rand::thread_rng().gen_range(range);
-        Ok(self.available_spawns.remove(random_index))
+    }
+
+    fn choose_spawn(spawns: &[Spawn], team: Option<Team>) -> Result<usize, Error> {
+        let mut candidates =
+            Self::find_spawns(spawns, |spawn| team.is_none() || spawn.team == team);
+        if candidates.is_empty() {
+            candidates = Self::find_spawns(spawns, |spawn| spawn.team.is_none());
+        }
+
+        candidates
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .ok_or_else(|| Error::new(NoSpawnAvailable, "no more spawns available"))
+    }
+
+    fn find_spawns(spawns: &[Spawn], is_candidate: impl Fn(&Spawn) -> bool) -> Vec<usize> {
+        spawns
+            .iter()
+            .enumerate()
+            .filter(|(_, spawn)| is_candidate(spawn))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The first spawn of a team is used as its base.
+    pub fn get_team_base(&self, team: Team) -> Option<Block> {
+        self.spawns
+            .iter()
+            .find(|spawn| spawn.team == Some(team))
+            .map(|spawn| spawn.block)
+    }
+
+    pub fn get_hill_blocks(&self) -> &[Block] {
+        &self.hill_blocks
+    }
+
+    /// Number of rings of blocks around the center of the map, the outer ring is 0.
+    pub fn number_of_rings(&self) -> u8 {
+        self.dimensions.0.min(self.dimensions.1) / 2 + 1
+    }
+
+    /// Turns the walkable blocks of a ring into hazards, walls and holes are kept.
+    pub fn close_ring(&mut self, ring: u8) {
+        let Dimensions(width, height) = self.dimensions;
+        for (y, row) in self.block_matrix.iter_mut().enumerate() {
+            for (x, block_kind) in row.iter_mut().enumerate() {
+                let (x, y) = (x as u8, y as u8);
+                let block_ring = x
+                    .min(y)
+                    .min(width.saturating_sub(x + 1))
+                    .min(height.saturating_sub(y + 1));
+                if block_ring == ring
+                    && matches!(
+                        block_kind,
+                        BlockKind::Ground | BlockKind::Water | BlockKind::Bush
+                    )
+                {
+                    *block_kind = BlockKind::Hazard;
+                }
+            }
+        }
+    }
+
+    /// Restores the spawns and the destroyed blocks for the next game.
+    pub fn reset(&mut self) {
+        self.available_spawns = self.spawns.clone();
+        self.block_matrix = self.initial_block_matrix.clone();
+    }
+
+    /// Picks a free block for a pickup, marked pickup blocks are used if the map has any.
+    pub fn get_pickup_block(&self, occupied_blocks: &[Block]) -> Option<Block> {
+        let candidates = if self.pickup_spawns.is_empty() {
+            &self.ground_blocks
+        } else {
+            &self.pickup_spawns
+        };
+        let free_blocks: Vec<&Block> = candidates
+            .iter()
+            .filter(|block| !occupied_blocks.contains(block))
+            .collect();
+        free_blocks
+            .choose(&mut rand::thread_rng())
+            .map(|block| **block)
+    }
+
+    pub fn width(&self) -> u8 {
+        self.dimensions.0
+    }
+
+    pub fn height(&self) -> u8 {
+        self.dimensions.1
     }
 
     pub fn block_size(&self) -> u32 {
         self.block_size
     }
 
-    pub fn is_wall(&self, block: &Block) -> bool {
-        self.wall_set.contains(block)
+    pub fn get_block_kind(&self, block: &Block) -> Option<&BlockKind> {
+        self.block_matrix
+            .get(block.1 as usize)
+            .and_then(|row| row.get(block.0 as usize))
+    }
+
+    pub fn hides_tanks(&self, block: &Block) -> bool {
+        self.get_block_kind(block)
+            .is_some_and(|block_kind| block_kind.hides_tanks())
+    }
+
+    pub fn is_hazard(&self, block: &Block) -> bool {
+        self.get_block_kind(block)
+            .is_some_and(|block_kind| block_kind.is_hazard())
+    }
+
+    pub fn speed_factor(&self, block: &Block) -> f64 {
+        self.get_block_kind(block)
+            .map_or(1., |block_kind| block_kind.speed_factor())
+    }
+
+    /// Removes a hit point from a destructible wall, returns false if the block cannot be damaged.
+    pub fn damage_block(&mut self, block: &Block) -> bool {
+        let block_kind = self
+            .block_matrix
+            .get_mut(block.1 as usize)
+            .and_then(|row| row.get_mut(block.0 as usize));
+
+        match block_kind {
+            Some(block_kind @ BlockKind::DestructibleWall(1)) => {
+                *block_kind = BlockKind::Ground;
+                true
+            }
+            Some(BlockKind::DestructibleWall(hit_points)) => {
+                *hit_points -= 1;
+                true
+            }
+            _ => false,
+        }
     }
 }
@@ -1,5 +1,13 @@
+pub mod collision_grid;
+pub mod feedback;
 pub mod game_state;
 pub mod map;
+pub mod round_timer;
+pub mod rules;
 
+pub use collision_grid::*;
+pub use feedback::*;
 pub use game_state::*;
 pub use map::*;
+pub use round_timer::*;
+pub use rules::*;
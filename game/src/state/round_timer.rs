@@ -0,0 +1,6 @@
+/// Number of frames since the beginning of the round. It is apart from the `State` so the
+/// systems only reading the state don't wait for the one counting the frames.
+#[derive(Default)]
+pub struct RoundTimer {
+    pub frames: u32,
+}
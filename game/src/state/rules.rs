@@ -0,0 +1,47 @@
+use serde::Deserialize;
+
+const DEFAULT_BULLET_BOUNCES: u32 = 1;
+const DEFAULT_SUDDEN_DEATH_AFTER_SECONDS: u32 = 120;
+
+/// Gameplay options that can change from one game to another, read from the `rules` section
+/// of the configuration.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Rules {
+    /// Number of times a bullet reflects off walls before despawning, 0 disables ricochets.
+    pub bullet_bounces: u32,
+    /// Weapons whose bullets are destroyed when they meet another interceptable bullet.
+    pub interceptable_weapons: Vec<Weapon>,
+    /// Enabled when the game mode uses teams or when players pick a team in the lobby.
+    #[serde(skip)]
+    pub team_mode: bool,
+    pub friendly_fire: bool,
+    /// Duration of a round before the map starts to shrink, `None` disables the sudden death.
+    pub sudden_death_after_seconds: Option<u32>,
+}
+
+impl Rules {
+    pub fn is_interceptable(&self, weapon: Weapon) -> bool {
+        self.interceptable_weapons.contains(&weapon)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            bullet_bounces: DEFAULT_BULLET_BOUNCES,
+            interceptable_weapons: vec![Weapon::Shell, Weapon::BouncingShell],
+            team_mode: false,
+            friendly_fire: false,
+            sudden_death_after_seconds: Some(DEFAULT_SUDDEN_DEATH_AFTER_SECONDS),
+        }
+    }
+}
+
+/// Weapon firing a bullet, the bouncing shells are fired while the bouncing ammo is picked up.
+#[derive(Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Weapon {
+    Shell,
+    BouncingShell,
+}
@@ -1,12 +1,26 @@
-use specs::{Entities, Entity, Join, ReadStorage, System, WriteExpect, WriteStorage};
-use tracing::{debug, error, trace, warn};
+use futures_channel::mpsc::UnboundedSender;
+use specs::{
+    Entities, Entity, Join, Read, ReadExpect, ReadStorage, ReaderId, System, SystemData, World,
+    Write, WriteExpect, WriteStorage,
+};
+use std::collections::HashMap;
+use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    components::{AimStatus, Bullet, Circle, Movement, Player, Position, ReadyStatus, ShootStatus},
-    remotes::{ConfigurationInput, GameInput, PlayerInput, RemoteInput},
+    components::{
+        Ability, AbilityKind, AimStatus, Bullet, Circle, Mine, Movement, Pickup, PickupKind,
+        Player, Position, ReadyStatus, ShootStatus, Team,
+    },
+    events::{GameEvent, GameEvents, KillFeed},
+    game_modes::{Kill, PlayerView},
+    remotes::{
+        ConfigurationInput, GameInput, MessageToPlayer, MessageToRemote, PlayerState,
+        ReceivedInputs, RemoteInput,
+    },
+    render::renderer,
     state::{
         game_state::{Phase, State},
-        Block, Map,
+        Block, BlockKind, CollisionGrid, FeedbackPatterns, Map, RoundTimer,
     },
 };
 
@@ -15,19 +29,19 @@ pub struct RetrievePlayerForInputs;
 impl<'a> System<'a> for RetrievePlayerForInputs {
     type SystemData = (
         Entities<'a>,
-        ReadStorage<'a, PlayerInput>,
+        Write<'a, ReceivedInputs>,
         WriteStorage<'a, Player>,
+        Write<'a, GameEvents>,
     );
 
-    fn run(&mut self, (entities, player_inputs, mut players): Self::SystemData) {
-        for (entity, input) in (&entities, &player_inputs).join() {
+    fn run(&mut self, (entities, mut received_inputs, mut players, mut events): Self::SystemData) {
+        for input in received_inputs.drain() {
             let player = self.retrieve_player_from_socket(&mut players, input.socket_id);
             trace!("Player inputs: {:?}", input);
 
-            // Update the players next input
+            // Queue the input, the players apply all their inputs of the frame in order
             if let Some(player) = player {
-                // Perform action if `option_value` is `Some`
-                player.next_input = input.remote_input.clone();
+                player.inputs.push(input.remote_input);
             } else {
                 if let RemoteInput::ConfigurationInput(ConfigurationInput::PlayerId(id)) =
                     &input.remote_input
@@ -40,13 +54,14 @@ impl<'a> System<'a> for RetrievePlayerForInputs {
                         players
                             .insert(player_entity, Player::new(input.socket_id, id.to_string()))
                             .unwrap();
+                        events.single_write(GameEvent::PlayerJoined {
+                            player_id: id.to_string(),
+                        });
                     }
                 } else {
                     warn!("Player with with socket id \"{}\"", input.socket_id);
                 }
             }
-            // delete the entity
-            entities.delete(entity).expect("Failed to delete entity");
         }
     }
 }
@@ -79,77 +94,99 @@ impl RetrievePlayerForInputs {
     }
 }
 
-pub struct HandleInputs;
+/// Phase controller: handles the configuration inputs before the game and starts the round
+/// once every player is ready.
+pub struct HandlePhase;
 
-impl<'a> System<'a> for HandleInputs {
+impl<'a> System<'a> for HandlePhase {
     type SystemData = (
         Entities<'a>,
         WriteStorage<'a, Player>,
-        WriteStorage<'a, Bullet>,
         WriteStorage<'a, Movement>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, Circle>,
         WriteExpect<'a, State>,
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, RoundTimer>,
     );
 
-    fn run(
-        &mut self,
-        (entities, players, bullet, movements, positions, circles, state): Self::SystemData,
-    ) {
-        match state.phase {
-            Phase::BeforeNextGame | Phase::BreakInGame => self.handle_configuration_inputs(
-                entities, players, movements, positions, circles, state,
-            ),
-            Phase::InGame => {
-                let (players, movements) = self.handle_game_inputs(players, movements);
-                self.update_game(
-                    entities, players, bullet, circles, movements, positions, state,
-                );
-            }
+    fn run(&mut self, data: Self::SystemData) {
+        match data.5.phase {
+            Phase::BeforeNextGame | Phase::BreakInGame => self.handle_configuration_inputs(data),
+            Phase::InGame => {}
         }
     }
 }
 
-impl HandleInputs {
+impl HandlePhase {
     fn handle_configuration_inputs<'a>(
         &self,
-        entities: Entities<'a>,
-        mut players: WriteStorage<'a, Player>,
-        mut movements: WriteStorage<'a, Movement>,
-        mut positions: WriteStorage<'a, Position>,
-        mut circles: WriteStorage<'a, Circle>,
-        mut state: WriteExpect<'a, State>,
+        (
+            entities,
+            mut players,
+            mut movements,
+            mut positions,
+            mut circles,
+            mut state,
+            mut map,
+            mut round_timer,
+        ): <Self as System<'a>>::SystemData,
     ) {
-        for mut player in (&mut players).join() {
-            match &player.next_input {
-                RemoteInput::GameInput(_) => {
-                    debug!("Game input is not allowed: Game hasn't started yet.")
-                }
-                RemoteInput::ConfigurationInput(ConfigurationInput::NotReady) => {
-                    player.status = ReadyStatus::NotReady;
-                }
-                RemoteInput::ConfigurationInput(ConfigurationInput::Ready) => {
-                    player.status = ReadyStatus::Ready;
-                }
-                RemoteInput::ConfigurationInput(ConfigurationInput::SetName(name)) => {
-                    if !name.is_empty() {
-                        player.name = name.to_string();
+        for player in (&mut players).join() {
+            for input in std::mem::take(&mut player.inputs) {
+                match input {
+                    RemoteInput::GameInput(_) => {
+                        debug!("Game input is not allowed: Game hasn't started yet.")
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::NotReady) => {
+                        player.status = ReadyStatus::NotReady;
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::Ready) => {
+                        player.status = ReadyStatus::Ready;
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::SetName(name)) => {
+                        if !name.is_empty() {
+                            player.name = name;
+                        }
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::SetTeam(team)) => {
+                        player.team = Some(team);
+                        player.is_team_assigned = false;
                     }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::LeaveTeam) => {
+                        player.team = None;
+                        player.is_team_assigned = false;
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::SetAbility(kind)) => {
+                        player.ability = Ability::new(kind);
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::SetGameMode(kind)) => {
+                        if state.game_mode.kind() != kind {
+                            info!("Game mode changed to \"{}\".", kind.name());
+                            state.game_mode = kind.create();
+                        }
+                    }
+                    RemoteInput::ConfigurationInput(ConfigurationInput::PlayerId(_)) => {}
                 }
-                RemoteInput::NoInput
-                | RemoteInput::ConfigurationInput(ConfigurationInput::PlayerId(_)) => {}
             }
         }
 
         if self.all_players_are_ready(&players) {
             state.phase = Phase::InGame;
+            round_timer.frames = 0;
+
+            state.rules.team_mode =
+                state.game_mode.uses_teams() || players.join().any(|player| player.team.is_some());
+            if state.rules.team_mode {
+                self.assign_teams(&mut players);
+            }
 
-            for (player_entity, _) in (&entities, &players).join() {
-                let spawn = state.map.get_spawn_block();
+            for (player_entity, player) in (&entities, &players).join() {
+                let spawn = map.get_spawn_block(player.team);
                 match spawn {
                     Err(err) => error!("Couldn't spawn player: {}.", err),
                     Ok(spawn) => {
-                        let spawn_position = get_position_block_center(spawn);
+                        let spawn_position = spawn.center();
 
                         positions.insert(player_entity, spawn_position).unwrap();
                         movements.insert(player_entity, Movement::new()).unwrap();
@@ -159,94 +196,349 @@ impl HandleInputs {
                     }
                 }
             }
+
+            let player_views: Vec<PlayerView> = (&players, positions.maybe())
+                .join()
+                .map(|(player, position)| PlayerView::new(player, position))
+                .collect();
+            state.game_mode.on_round_start(&player_views, &map);
+        }
+    }
+
+    /// Puts the players who didn't choose a team in the smallest team.
+    fn assign_teams(&self, players: &mut WriteStorage<Player>) {
+        let count_members = |players: &WriteStorage<Player>, team| {
+            players
+                .join()
+                .filter(|player| player.team == Some(team))
+                .count()
+        };
+        let mut number_of_red_players = count_members(players, Team::Red);
+        let mut number_of_blue_players = count_members(players, Team::Blue);
+
+        for player in players.join().filter(|player| player.team.is_none()) {
+            player.is_team_assigned = true;
+            if number_of_red_players <= number_of_blue_players {
+                player.team = Some(Team::Red);
+                number_of_red_players += 1;
+            } else {
+                player.team = Some(Team::Blue);
+                number_of_blue_players += 1;
+            }
         }
     }
 
     fn all_players_are_ready(&self, players: &WriteStorage<Player>) -> bool {
         !&players.is_empty() && players.join().all(|player| player.is_ready())
     }
+}
+
+/// Input mapping: turns the game inputs of the players into movements, aims, shots, reloads
+/// and abilities.
+pub struct MapGameInputs;
+
+impl<'a> System<'a> for MapGameInputs {
+    type SystemData = (
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Movement>,
+        ReadExpect<'a, State>,
+    );
+
+    fn run(&mut self, (mut players, mut movements, state): Self::SystemData) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
 
-    fn handle_game_inputs<'a>(
-        &self,
-        mut players: WriteStorage<'a, Player>,
-        mut movements: WriteStorage<'a, Movement>,
-    ) -> (WriteStorage<'a, Player>, WriteStorage<'a, Movement>) {
         for (player, movement) in (&mut players, &mut movements).join() {
-            match player.next_input {
-                RemoteInput::GameInput(GameInput::Move(direction)) => {
-                    movement.set_player_direction(direction);
-                }
-                RemoteInput::GameInput(GameInput::Stop) => movement.stop(),
-                RemoteInput::GameInput(GameInput::Aim(direction)) => {
-                    player.aim = AimStatus::Aim(direction);
+            for input in std::mem::take(&mut player.inputs) {
+                match input {
+                    RemoteInput::GameInput(
+                        GameInput::Move(_) | GameInput::Aim(_) | GameInput::Shoot,
+                    ) if !player.is_alive => {
+                        trace!("game input ignored: player is dead")
+                    }
+                    RemoteInput::GameInput(GameInput::Move(direction)) => {
+                        movement.set_player_direction(direction, player.speed_factor());
+                    }
+                    RemoteInput::GameInput(GameInput::Stop) => movement.stop(),
+                    RemoteInput::GameInput(GameInput::Aim(direction)) => {
+                        player.aim = AimStatus::Aim(direction);
+                    }
+                    RemoteInput::GameInput(GameInput::Shoot) => {
+                        if player.shoot == ShootStatus::CanShoot && player.ammo.in_magazine > 0 {
+                            trace!("Player is gonna shoot");
+                            player.shoot = ShootStatus::Shooting;
+                        } else {
+                            player.aim = AimStatus::None;
+                        }
+                    }
+                    RemoteInput::GameInput(GameInput::Reload) => {
+                        if !player.is_reloading() && player.ammo.can_reload() {
+                            trace!("Player is reloading");
+                            player.start_reload();
+                        }
+                    }
+                    RemoteInput::GameInput(GameInput::UseAbility) => {
+                        if player.is_alive && player.ability.is_ready() {
+                            trace!("Player uses the ability {}", player.ability.kind.name());
+                            player.ability.activate();
+                        }
+                    }
+                    RemoteInput::ConfigurationInput(_) => {
+                        trace!("configuration input not allowed: game has started")
+                    }
                 }
-                RemoteInput::GameInput(GameInput::Shoot) => {
-                    if player.shoot == ShootStatus::CanShoot {
-                        trace!("Player is gonna shoot");
-                        player.shoot = ShootStatus::Shooting;
-                    } else {
-                        player.aim = AimStatus::None;
+            }
+        }
+    }
+}
+
+/// Moves the tanks and the bullets, tanks are stopped by the walls and the other tanks while
+/// bullets ricochet or damage the walls.
+pub struct MoveEntities;
+
+impl<'a> System<'a> for MoveEntities {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Bullet>,
+        ReadStorage<'a, Circle>,
+        WriteStorage<'a, Movement>,
+        WriteStorage<'a, Position>,
+        ReadExpect<'a, CollisionGrid>,
+        Write<'a, GameEvents>,
+        ReadExpect<'a, State>,
+        WriteExpect<'a, Map>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            players,
+            mut bullets,
+            circles,
+            mut movements,
+            mut positions,
+            grid,
+            mut events,
+            state,
+            mut map,
+        ): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+
+        // Wall detection
+        for (entity, circle, movement, position, bullet) in (
+            &entities,
+            &circles,
+            &mut movements,
+            &mut positions,
+            (&mut bullets).maybe(),
+        )
+            .join()
+        {
+            if let Some(bullet) = bullet {
+                let next_position = position.next(movement);
+                match find_block_collision(&next_position, circle, &map, BlockKind::blocks_bullets)
+                {
+                    None => position.update(&next_position),
+                    Some(block) => {
+                        events.single_write(GameEvent::BulletHitWall {
+                            block,
+                            position: next_position,
+                        });
+                        if map.damage_block(&block) {
+                            entities.delete(entity).expect("Failed to delete bullet");
+                        } else if bullet.bounces_left > 0 {
+                            reflect_on_wall(position, movement, circle, &map);
+                            bullet.bounce();
+                        } else {
+                            entities.delete(entity).expect("Failed to delete bullet");
+                        }
                     }
                 }
-                RemoteInput::ConfigurationInput(_) => {
-                    trace!("configuration input not allowed: game has started")
+            } else if players.get(entity).is_none_or(|player| player.is_alive) {
+                // Dead tanks stay where they were killed until their respawn
+                let speed_factor = map.speed_factor(&position.block());
+                let next_position = position.next_slowed(movement, speed_factor);
+                // Alive tanks cannot drive through each other, tanks respawned on top of each
+                // other can still drive apart
+                let is_blocked_by_tank = players.get(entity).is_some_and(|player| player.is_alive)
+                    && grid
+                        .query(&next_position, circle.get_radius())
+                        .iter()
+                        .any(|entry| {
+                            entry.entity != entity
+                                && players
+                                    .get(entry.entity)
+                                    .is_some_and(|other| other.is_alive)
+                                && entry.overlaps(&next_position, circle.get_radius())
+                                && !entry.overlaps(position, circle.get_radius())
+                        });
+                if !is_blocked_by_tank
+                    && find_block_collision(&next_position, circle, &map, BlockKind::blocks_tanks)
+                        .is_none()
+                {
+                    position.update(&next_position);
                 }
-                RemoteInput::NoInput => {}
             }
-
-            player.next_input = RemoteInput::NoInput;
         }
+    }
+}
+
+/// Rebuilds the collision grid from the positions of the entities after they moved.
+pub struct UpdateCollisionGrid;
 
-        (players, movements)
+impl<'a> System<'a> for UpdateCollisionGrid {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Circle>,
+        WriteExpect<'a, CollisionGrid>,
+    );
+
+    fn run(&mut self, (entities, positions, circles, mut grid): Self::SystemData) {
+        grid.clear();
+        for (entity, position, circle) in (&entities, &positions, &circles).join() {
+            grid.insert(entity, position, circle.get_radius());
+        }
     }
+}
 
-    fn update_game<'a>(
-        &self,
-        entities: Entities<'a>,
-        mut players: WriteStorage<Player>,
-        mut bullets: WriteStorage<'a, Bullet>,
-        mut circles: WriteStorage<'a, Circle>,
-        mut movements: WriteStorage<'a, Movement>,
-        mut positions: WriteStorage<'a, Position>,
-        state: WriteExpect<'a, State>,
+/// Bullets intercepting each other and hitting the players, the kills are sent as events.
+pub struct HandleCollisions;
+
+impl<'a> System<'a> for HandleCollisions {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Bullet>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Circle>,
+        ReadExpect<'a, CollisionGrid>,
+        Write<'a, GameEvents>,
+        ReadExpect<'a, State>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut players, bullets, positions, circles, grid, mut events, state): Self::SystemData,
     ) {
-        // Wall detection
-        for (circle, movement, position) in (&mut circles, &mut movements, &mut positions).join() {
-            let next_position = position.next(movement);
-            if !has_wall_collision(&next_position, circle, &state.map) {
-                position.update(&next_position);
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+
+        // Bullet - Bullet collision
+        let mut intercepted_bullets = Vec::new();
+        for (entity, position, circle, bullet) in (&entities, &positions, &circles, &bullets).join()
+        {
+            let is_intercepted = bullet.interceptable
+                && grid
+                    .query(position, circle.get_radius())
+                    .iter()
+                    .any(|entry| {
+                        entry.entity != entity
+                            && bullets
+                                .get(entry.entity)
+                                .is_some_and(|other| other.interceptable)
+                            && entry.overlaps(position, circle.get_radius())
+                    });
+            if is_intercepted {
+                intercepted_bullets.push(entity);
+                entities.delete(entity).expect("Failed to delete bullet");
             }
         }
 
         // Bullet - Player collision
         for (player_circle, player_position, player) in (&circles, &positions, &mut players).join()
         {
-            for (bullet_circle, bullet_position, bullet) in (&circles, &positions, &bullets).join()
-            {
-                if has_bullet_player_collision(
-                    player_position,
-                    player_circle,
-                    bullet_position,
-                    bullet_circle,
-                ) && bullet.owner_id != player.id
+            for entry in grid.query(player_position, player_circle.get_radius()) {
+                let Some(bullet) = bullets.get(entry.entity) else {
+                    continue;
+                };
+                if player.is_alive
+                    && !intercepted_bullets.contains(&entry.entity)
+                    && entry.overlaps(player_position, player_circle.get_radius())
+                    && bullet.can_hit(player)
+                    && (state.rules.friendly_fire || !bullet.is_from_teammate(player))
                 {
-                    player.is_alive = false;
+                    if player.hit() {
+                        events.single_write(GameEvent::PlayerKilled(Kill {
+                            killer_id: bullet.owner_id.clone(),
+                            killer_team: bullet.owner_team,
+                            victim: PlayerView::new(player, Some(player_position)),
+                        }));
+                    } else {
+                        events.single_write(GameEvent::PlayerHit {
+                            player_id: player.id.clone(),
+                        });
+                    }
+                    entities
+                        .delete(entry.entity)
+                        .expect("Failed to delete bullet");
                 }
             }
         }
+    }
+}
+
+/// Fires the bullets of the players who are shooting.
+pub struct HandleShooting;
+
+impl<'a> System<'a> for HandleShooting {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Bullet>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Movement>,
+        WriteStorage<'a, Circle>,
+        Write<'a, GameEvents>,
+        ReadExpect<'a, State>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut players,
+            mut bullets,
+            mut positions,
+            mut movements,
+            mut circles,
+            mut events,
+            state,
+        ): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
 
-        // Generate new bullets
         let mut new_bullets = vec![];
         for (player, position) in (&mut players, &positions).join() {
-            if player.shoot == ShootStatus::Shooting {
+            if player.is_alive && player.shoot == ShootStatus::Shooting {
                 match player.aim {
-                    AimStatus::Aim(direction) => new_bullets.push(BulletData {
-                        entity: entities.create(),
-                        bullet: Bullet::new(player.id.clone()),
-                        position: position.clone(),
-                        movement: Movement::new_bullet_movement(direction),
-                        circle: Circle::new_bullet_circle(),
-                    }),
+                    AimStatus::Aim(direction) => {
+                        new_bullets.push(BulletData {
+                            entity: entities.create(),
+                            bullet: Bullet::new(
+                                player,
+                                player.bullet_bounces(state.rules.bullet_bounces),
+                                state.rules.is_interceptable(player.weapon()),
+                            ),
+                            position: position.clone(),
+                            movement: Movement::new_bullet_movement(direction),
+                            circle: Circle::new_bullet_circle(),
+                        });
+                        player.ammo.use_round();
+                        events.single_write(GameEvent::BulletFired {
+                            owner_id: player.id.clone(),
+                            position: position.clone(),
+                        });
+                    }
                     AimStatus::None => {
                         trace!("player must be aiming when shooting")
                     }
@@ -260,51 +552,642 @@ impl HandleInputs {
             movements.insert(bullet.entity, bullet.movement).unwrap();
             circles.insert(bullet.entity, bullet.circle).unwrap();
         }
+    }
+}
+
+/// Counts down the delay between two shots and the reloads.
+pub struct HandleCooldowns;
+
+impl<'a> System<'a> for HandleCooldowns {
+    type SystemData = (WriteStorage<'a, Player>, ReadExpect<'a, State>);
+
+    fn run(&mut self, (mut players, state): Self::SystemData) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
 
-        // Update players
         for player in (&mut players).join() {
-            if let ShootStatus::FrameLeftUntilNextShot(number_of_frames) = player.shoot {
-                if number_of_frames > 0 {
-                    player.shoot = ShootStatus::FrameLeftUntilNextShot(number_of_frames - 1);
-                } else {
-                    player.shoot = ShootStatus::CanShoot;
+            match player.shoot {
+                ShootStatus::FrameLeftUntilNextShot(number_of_frames) => {
+                    if number_of_frames > 0 {
+                        player.shoot = ShootStatus::FrameLeftUntilNextShot(number_of_frames - 1);
+                    } else {
+                        player.shoot = ShootStatus::CanShoot;
+                    }
+                }
+                ShootStatus::Reloading(number_of_frames) => {
+                    if number_of_frames > 0 {
+                        player.shoot = ShootStatus::Reloading(number_of_frames - 1);
+                    } else {
+                        player.ammo.reload();
+                        player.shoot = ShootStatus::CanShoot;
+                    }
                 }
+                ShootStatus::CanShoot | ShootStatus::Shooting => {}
             }
         }
     }
 }
 
-fn get_position_block_center(block: Block) -> Position {
-    Position {
-        x: 0.5 + block.0 as f64,
-        y: 0.5 + block.1 as f64,
+/// Handles the `PlayerKilled` events: the victims wait for their respawn and the game mode is
+/// notified.
+#[derive(Default)]
+pub struct HandleDeaths {
+    reader: Option<ReaderId<GameEvent>>,
+}
+
+impl<'a> System<'a> for HandleDeaths {
+    type SystemData = (
+        WriteStorage<'a, Player>,
+        Read<'a, GameEvents>,
+        WriteExpect<'a, State>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<GameEvents>().register_reader());
+    }
+
+    fn run(&mut self, (mut players, events, mut state): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("HandleDeaths has not been set up");
+        for event in events.read(reader) {
+            if let GameEvent::PlayerKilled(kill) = event {
+                if let Some(victim) = (&mut players)
+                    .join()
+                    .find(|player| player.id == kill.victim.id)
+                {
+                    victim.frames_until_respawn = state.game_mode.respawn_delay();
+                }
+                state.game_mode.on_kill(kill);
+            }
+        }
     }
 }
 
-fn has_wall_collision(position: &Position, circle: &Circle, map: &Map) -> bool {
-    for hit_point in circle.hit_points.iter() {
-        let block = &Block(
-            (position.x + hit_point.x) as u8,
-            (position.y + hit_point.y) as u8,
-        );
-        if map.is_wall(block) {
-            return true;
+/// Keeps the kill feed displayed in game up to date from the gameplay events.
+#[derive(Default)]
+pub struct UpdateKillFeed {
+    reader: Option<ReaderId<GameEvent>>,
+}
+
+impl<'a> System<'a> for UpdateKillFeed {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        Read<'a, GameEvents>,
+        Write<'a, KillFeed>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<GameEvents>().register_reader());
+    }
+
+    fn run(&mut self, (players, events, mut kill_feed): Self::SystemData) {
+        kill_feed.update();
+
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("UpdateKillFeed has not been set up");
+        for event in events.read(reader) {
+            match event {
+                GameEvent::PlayerKilled(kill) if kill.killer_id == kill.victim.id => {
+                    kill_feed.push(format!("{} died", kill.victim.name));
+                }
+                GameEvent::PlayerKilled(kill) => {
+                    let killer_name = players
+                        .join()
+                        .find(|player| player.id == kill.killer_id)
+                        .map_or("Someone", |player| &player.name);
+                    kill_feed.push(format!("{} killed {}", killer_name, kill.victim.name));
+                }
+                GameEvent::RoundEnded { .. } => kill_feed.clear(),
+                GameEvent::PlayerJoined { .. }
+                | GameEvent::PlayerHit { .. }
+                | GameEvent::BulletFired { .. }
+                | GameEvent::BulletHitWall { .. } => {}
+            }
+        }
+    }
+}
+
+/// Sends to each remote the personal state of its player when it changed since the last frame.
+pub struct SendPlayerStates {
+    sender: UnboundedSender<MessageToPlayer>,
+    last_states: HashMap<u32, PlayerState>,
+}
+
+impl SendPlayerStates {
+    pub fn new(sender: UnboundedSender<MessageToPlayer>) -> Self {
+        SendPlayerStates {
+            sender,
+            last_states: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for SendPlayerStates {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        ReadExpect<'a, State>,
+    );
+
+    fn run(&mut self, (players, positions, state): Self::SystemData) {
+        let player_views: Vec<PlayerView> = (&players, positions.maybe())
+            .join()
+            .map(|(player, position)| PlayerView::new(player, position))
+            .collect();
+        let scores = state.game_mode.scores(&player_views);
+
+        for (player, position) in (&players, positions.maybe()).join() {
+            let side = PlayerView::new(player, position).side();
+            let player_state = PlayerState {
+                phase: state.phase,
+                is_ready: player.is_ready(),
+                is_alive: player.is_alive,
+                extra_lives: player.extra_lives,
+                rounds_in_magazine: player.ammo.in_magazine,
+                reserve_ammo: player.ammo.reserve,
+                is_reloading: player.is_reloading(),
+                // Rounded to tenths so the state isn't sent every frame during the cooldown
+                ability_progress: (player.ability.cooldown_progress() * 10.).floor() / 10.,
+                score: scores
+                    .iter()
+                    .find(|score| score.side == side)
+                    .map(|score| score.points),
+            };
+            if self.last_states.get(&player.socket_id) == Some(&player_state) {
+                continue;
+            }
+
+            let message = MessageToPlayer {
+                socket_id: player.socket_id,
+                message: MessageToRemote::PlayerState(player_state.clone()),
+            };
+            if let Err(err) = self.sender.unbounded_send(message) {
+                error!("Couldn't send the player state: {}.", err);
+            }
+            self.last_states.insert(player.socket_id, player_state);
         }
     }
+}
 
-    false
+/// Sends a feedback to the remotes of the players who are hit, shoot or die.
+pub struct SendFeedback {
+    sender: UnboundedSender<MessageToPlayer>,
+    patterns: FeedbackPatterns,
+    reader: Option<ReaderId<GameEvent>>,
 }
 
-fn has_bullet_player_collision(
-    player_position: &Position,
-    player_circle: &Circle,
-    bullet_position: &Position,
-    bullet_circle: &Circle,
-) -> bool {
-    let distance = ((player_position.x - bullet_position.x).powi(2)
-        + (player_position.y - bullet_position.y).powi(2))
-    .sqrt();
-    distance < player_circle.get_radius() + bullet_circle.get_radius()
+impl SendFeedback {
+    pub fn new(sender: UnboundedSender<MessageToPlayer>, patterns: FeedbackPatterns) -> Self {
+        SendFeedback {
+            sender,
+            patterns,
+            reader: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for SendFeedback {
+    type SystemData = (ReadStorage<'a, Player>, Read<'a, GameEvents>);
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(world.fetch_mut::<GameEvents>().register_reader());
+    }
+
+    fn run(&mut self, (players, events): Self::SystemData) {
+        let reader = self
+            .reader
+            .as_mut()
+            .expect("SendFeedback has not been set up");
+        for event in events.read(reader) {
+            let (player_id, feedback) = match event {
+                GameEvent::PlayerHit { player_id } => (player_id, &self.patterns.hit),
+                GameEvent::BulletFired { owner_id, .. } => (owner_id, &self.patterns.shot),
+                GameEvent::PlayerKilled(kill) => (&kill.victim.id, &self.patterns.death),
+                GameEvent::PlayerJoined { .. }
+                | GameEvent::BulletHitWall { .. }
+                | GameEvent::RoundEnded { .. } => continue,
+            };
+            let Some(player) = players.join().find(|player| &player.id == player_id) else {
+                continue;
+            };
+
+            let message = MessageToPlayer {
+                socket_id: player.socket_id,
+                message: MessageToRemote::Feedback(feedback.clone()),
+            };
+            if let Err(err) = self.sender.unbounded_send(message) {
+                error!("Couldn't send the feedback: {}.", err);
+            }
+        }
+    }
+}
+
+const SECONDS_BETWEEN_PICKUPS: u32 = 8;
+const MAX_PICKUPS_ON_MAP: usize = 3;
+
+pub struct HandlePickups {
+    frames_until_next_pickup: u32,
+}
+
+impl Default for HandlePickups {
+    fn default() -> Self {
+        HandlePickups {
+            frames_until_next_pickup: SECONDS_BETWEEN_PICKUPS * renderer::FRAME_PER_SECOND,
+        }
+    }
+}
+
+impl<'a> System<'a> for HandlePickups {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Pickup>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Circle>,
+        ReadExpect<'a, CollisionGrid>,
+        ReadExpect<'a, State>,
+        ReadExpect<'a, Map>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut pickups, mut players, mut positions, mut circles, grid, state, map): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+
+        // Spawn new pickups
+        if self.frames_until_next_pickup > 0 {
+            self.frames_until_next_pickup -= 1;
+        } else {
+            self.frames_until_next_pickup = SECONDS_BETWEEN_PICKUPS * renderer::FRAME_PER_SECOND;
+            if pickups.count() < MAX_PICKUPS_ON_MAP {
+                let occupied_blocks: Vec<Block> = (&pickups, &positions)
+                    .join()
+                    .map(|(_, position)| position.block())
+                    .collect();
+                if let Some(block) = map.get_pickup_block(&occupied_blocks) {
+                    let kind = PickupKind::random();
+                    let pickup_entity = entities.create();
+                    pickups.insert(pickup_entity, Pickup::new(kind)).unwrap();
+                    positions.insert(pickup_entity, block.center()).unwrap();
+                    circles
+                        .insert(pickup_entity, Circle::new_pickup_circle())
+                        .unwrap();
+                }
+            }
+        }
+
+        // Pickup - Player collision
+        for (pickup_entity, pickup, pickup_position, pickup_circle) in
+            (&entities, &pickups, &positions, &circles).join()
+        {
+            for entry in grid.query(pickup_position, pickup_circle.get_radius()) {
+                let Some(player) = players.get_mut(entry.entity) else {
+                    continue;
+                };
+                if player.is_alive && entry.overlaps(pickup_position, pickup_circle.get_radius()) {
+                    debug!("Player \"{}\" picked up a power-up.", player.name);
+                    player.apply_pickup(pickup.kind);
+                    entities
+                        .delete(pickup_entity)
+                        .expect("Failed to delete pickup");
+                    break;
+                }
+            }
+        }
+
+        // Update the timed effects
+        for player in (&mut players).join() {
+            player.update_effects();
+        }
+    }
+}
+
+/// Applies the abilities used by the players and triggers the mines dropped on the map.
+pub struct HandleAbilities;
+
+impl<'a> System<'a> for HandleAbilities {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Mine>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Movement>,
+        WriteStorage<'a, Circle>,
+        ReadExpect<'a, CollisionGrid>,
+        Write<'a, GameEvents>,
+        ReadExpect<'a, State>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut mines,
+            mut players,
+            mut positions,
+            mut movements,
+            mut circles,
+            grid,
+            mut events,
+            state,
+        ): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+
+        // Apply the abilities used since the last frame
+        let mut new_mines = Vec::new();
+        for (player, position, movement) in (&mut players, &positions, &mut movements).join() {
+            if player.ability.triggered {
+                player.ability.triggered = false;
+                match player.ability.kind {
+                    AbilityKind::Mine => new_mines.push((Mine::new(player), position.clone())),
+                    AbilityKind::Dash => movement.set_player_speed(player.speed_factor()),
+                    AbilityKind::Shield | AbilityKind::Smoke => {}
+                }
+            }
+
+            let was_dashing = player.ability.is_active(AbilityKind::Dash);
+            player.ability.update();
+            if was_dashing && !player.ability.is_active(AbilityKind::Dash) {
+                movement.set_player_speed(player.speed_factor());
+            }
+        }
+        for (mine, position) in new_mines {
+            let mine_entity = entities.create();
+            mines.insert(mine_entity, mine).unwrap();
+            positions.insert(mine_entity, position).unwrap();
+            circles
+                .insert(mine_entity, Circle::new_mine_circle())
+                .unwrap();
+        }
+
+        // Mine - Player collision
+        for (mine_entity, mine, mine_position, mine_circle) in
+            (&entities, &mut mines, &positions, &circles).join()
+        {
+            mine.update();
+            if !mine.is_armed() {
+                continue;
+            }
+            for entry in grid.query(mine_position, mine_circle.get_radius()) {
+                let Some(player) = players.get_mut(entry.entity) else {
+                    continue;
+                };
+                if player.is_alive
+                    && mine.is_triggered_by(player, state.rules.friendly_fire)
+                    && entry.overlaps(mine_position, mine_circle.get_radius())
+                {
+                    debug!("Player \"{}\" triggered a mine.", player.name);
+                    if player.hit() {
+                        events.single_write(GameEvent::PlayerKilled(Kill {
+                            killer_id: mine.owner_id.clone(),
+                            killer_team: mine.owner_team,
+                            victim: PlayerView::new(player, Some(&entry.position)),
+                        }));
+                    } else {
+                        events.single_write(GameEvent::PlayerHit {
+                            player_id: player.id.clone(),
+                        });
+                    }
+                    entities.delete(mine_entity).expect("Failed to delete mine");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+const SECONDS_BETWEEN_RING_CLOSINGS: u32 = 5;
+const FRAMES_BETWEEN_HAZARD_DAMAGES: u32 = renderer::FRAME_PER_SECOND;
+
+/// Once the sudden death starts, closes the rings of the map one after the other from the
+/// outside and damages the players standing on the closed blocks.
+pub struct HandleSuddenDeath;
+
+impl<'a> System<'a> for HandleSuddenDeath {
+    type SystemData = (
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        Write<'a, GameEvents>,
+        ReadExpect<'a, State>,
+        ReadExpect<'a, RoundTimer>,
+        WriteExpect<'a, Map>,
+    );
+
+    fn run(
+        &mut self,
+        (mut players, positions, mut events, state, round_timer, mut map): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+        let Some(sudden_death_after_seconds) = state.rules.sudden_death_after_seconds else {
+            return;
+        };
+        let Some(frames_since_sudden_death) = round_timer
+            .frames
+            .checked_sub(sudden_death_after_seconds * renderer::FRAME_PER_SECOND)
+        else {
+            return;
+        };
+
+        // The innermost ring stays open
+        let frames_between_ring_closings =
+            SECONDS_BETWEEN_RING_CLOSINGS * renderer::FRAME_PER_SECOND;
+        let ring = frames_since_sudden_death / frames_between_ring_closings;
+        if frames_since_sudden_death % frames_between_ring_closings == 0
+            && ring + 1 < map.number_of_rings() as u32
+        {
+            debug!("Sudden death closes the ring {}.", ring);
+            map.close_ring(ring as u8);
+        }
+
+        if frames_since_sudden_death % FRAMES_BETWEEN_HAZARD_DAMAGES != 0 {
+            return;
+        }
+        for (player, position) in (&mut players, &positions).join() {
+            if !player.is_alive || !map.is_hazard(&position.block()) {
+                continue;
+            }
+            if player.hit() {
+                // Players killed by the map count as suicides
+                events.single_write(GameEvent::PlayerKilled(Kill {
+                    killer_id: player.id.clone(),
+                    killer_team: player.team,
+                    victim: PlayerView::new(player, Some(position)),
+                }));
+            } else {
+                events.single_write(GameEvent::PlayerHit {
+                    player_id: player.id.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Runs the selected game mode: respawns, objectives and end of the round.
+pub struct HandleGameMode;
+
+impl<'a> System<'a> for HandleGameMode {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Bullet>,
+        ReadStorage<'a, Pickup>,
+        ReadStorage<'a, Mine>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Movement>,
+        WriteStorage<'a, Circle>,
+        Write<'a, GameEvents>,
+        WriteExpect<'a, State>,
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, RoundTimer>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut players,
+            bullets,
+            pickups,
+            mines,
+            mut positions,
+            mut movements,
+            mut circles,
+            mut events,
+            mut state,
+            mut map,
+            mut round_timer,
+        ): Self::SystemData,
+    ) {
+        if !matches!(state.phase, Phase::InGame) {
+            return;
+        }
+
+        // Respawn dead players
+        for (player, position, movement) in (&mut players, &mut positions, &mut movements).join() {
+            match player.frames_until_respawn {
+                Some(0) => match map.get_respawn_block(player.team) {
+                    Err(err) => error!("Couldn't respawn player: {}.", err),
+                    Ok(spawn) => {
+                        player.respawn();
+                        position.update(&spawn.center());
+                        movement.stop();
+                    }
+                },
+                Some(frames) => player.frames_until_respawn = Some(frames - 1),
+                None => {}
+            }
+        }
+
+        let player_views: Vec<PlayerView> = (&players, positions.maybe())
+            .join()
+            .map(|(player, position)| PlayerView::new(player, position))
+            .collect();
+        round_timer.frames += 1;
+        state.game_mode.on_tick(&player_views, &map);
+        if !state.game_mode.is_round_over(&player_views) {
+            return;
+        }
+
+        let winner = state
+            .game_mode
+            .winner(&player_views)
+            .unwrap_or("Nobody".to_owned());
+        info!("The game has ended, {} won.", winner);
+        events.single_write(GameEvent::RoundEnded {
+            winner: winner.clone(),
+        });
+        state.last_winner = Some(winner);
+        state.phase = Phase::BeforeNextGame;
+        map.reset();
+
+        for (entity, _) in (&entities, &bullets).join() {
+            entities.delete(entity).expect("Failed to delete bullet");
+        }
+        for (entity, _) in (&entities, &pickups).join() {
+            entities.delete(entity).expect("Failed to delete pickup");
+        }
+        for (entity, _) in (&entities, &mines).join() {
+            entities.delete(entity).expect("Failed to delete mine");
+        }
+        for (entity, player) in (&entities, &mut players).join() {
+            player.reset_for_next_game();
+            positions.remove(entity);
+            movements.remove(entity);
+            circles.remove(entity);
+        }
+    }
+}
+
+/// Returns the first block overlapped by the circle which is blocking according to `is_blocking`.
+/// Only the blocks under the bounding box of the circle are tested.
+fn find_block_collision(
+    position: &Position,
+    circle: &Circle,
+    map: &Map,
+    is_blocking: fn(&BlockKind) -> bool,
+) -> Option<Block> {
+    let radius = circle.get_radius();
+    let (min_x, max_x) = ((position.x - radius).max(0.), (position.x + radius).max(0.));
+    let (min_y, max_y) = ((position.y - radius).max(0.), (position.y + radius).max(0.));
+
+    for y in min_y as u8..=max_y as u8 {
+        for x in min_x as u8..=max_x as u8 {
+            let block = Block(x, y);
+            // Closest point of the block to the center of the circle
+            let closest_x = position.x.clamp(x as f64, x as f64 + 1.);
+            let closest_y = position.y.clamp(y as f64, y as f64 + 1.);
+            let is_overlapping = (position.x - closest_x).powi(2)
+                + (position.y - closest_y).powi(2)
+                < radius.powi(2);
+            if is_overlapping && map.get_block_kind(&block).is_some_and(is_blocking) {
+                return Some(block);
+            }
+        }
+    }
+
+    None
+}
+
+/// Reflects the movement against the edges of the wall tiles it is about to enter.
+/// Each axis is tested on its own, if neither collides alone the bullet hit a corner.
+fn reflect_on_wall(position: &Position, movement: &mut Movement, circle: &Circle, map: &Map) {
+    let next_position = position.clone().next(movement);
+    let horizontal_move = Position {
+        x: next_position.x,
+        y: position.y,
+    };
+    let vertical_move = Position {
+        x: position.x,
+        y: next_position.y,
+    };
+
+    match (
+        find_block_collision(&horizontal_move, circle, map, BlockKind::blocks_bullets).is_some(),
+        find_block_collision(&vertical_move, circle, map, BlockKind::blocks_bullets).is_some(),
+    ) {
+        (true, false) => movement.reflect_horizontally(),
+        (false, true) => movement.reflect_vertically(),
+        _ => {
+            movement.reflect_horizontally();
+            movement.reflect_vertically();
+        }
+    }
 }
 
 struct BulletData {
@@ -314,3 +1197,89 @@ struct BulletData {
     movement: Movement,
     circle: Circle,
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, RunNow, WorldExt};
+
+    use super::*;
+    use crate::{
+        game_modes::GameModeKind, remotes::PlayerInput, room_code::RoomCode,
+        server_communicator::ServerStatus, state::Rules,
+    };
+
+    const SOCKET_ID: u32 = 1;
+
+    fn create_world(phase: Phase) -> World {
+        let mut world = World::new();
+        world.register::<Player>();
+        world.register::<Movement>();
+        world.insert(State {
+            room_code: RoomCode::new("test".to_owned()),
+            phase,
+            number_of_ready_players: 0,
+            resolution: (1280, 720),
+            rules: Rules::default(),
+            last_winner: None,
+            game_mode: GameModeKind::LastTankStanding.create(),
+            show_debug_overlay: false,
+            server_status: ServerStatus::Connecting,
+        });
+        world.insert(ReceivedInputs::default());
+        world
+            .create_entity()
+            .with(Player::new(SOCKET_ID, "player".to_owned()))
+            .with(Movement::new())
+            .build();
+        world
+    }
+
+    fn run_system(mut system: impl for<'a> System<'a>, world: &mut World) {
+        System::setup(&mut system, world);
+        system.run_now(world);
+    }
+
+    fn send_game_inputs(world: &mut World, inputs: Vec<GameInput>) {
+        for input in inputs {
+            world.write_resource::<ReceivedInputs>().push(PlayerInput {
+                socket_id: SOCKET_ID,
+                remote_input: RemoteInput::GameInput(input),
+            });
+        }
+        run_system(RetrievePlayerForInputs, world);
+        run_system(MapGameInputs, world);
+    }
+
+    #[test]
+    fn all_inputs_of_a_frame_are_applied_in_order() {
+        let mut world = create_world(Phase::InGame);
+
+        send_game_inputs(&mut world, vec![GameInput::Aim(1.), GameInput::Shoot]);
+
+        let players = world.read_storage::<Player>();
+        let player = players.join().next().unwrap();
+        assert!(player.aim == AimStatus::Aim(1.));
+        assert!(player.shoot == ShootStatus::Shooting);
+        assert!(player.inputs.is_empty());
+    }
+
+    #[test]
+    fn inputs_of_dead_players_are_ignored() {
+        let mut world = create_world(Phase::InGame);
+        for player in (&mut world.write_storage::<Player>()).join() {
+            player.is_alive = false;
+        }
+
+        send_game_inputs(
+            &mut world,
+            vec![GameInput::Move(1.), GameInput::Aim(1.), GameInput::Shoot],
+        );
+
+        let players = world.read_storage::<Player>();
+        let movements = world.read_storage::<Movement>();
+        let (player, movement) = (&players, &movements).join().next().unwrap();
+        assert!(player.aim == AimStatus::None);
+        assert!(player.shoot == ShootStatus::CanShoot);
+        assert!(!movement.is_moving());
+    }
+}
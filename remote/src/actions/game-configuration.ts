@@ -1,5 +1,5 @@
-import { sendStringToGame, sendToGame } from "../api/game";
-import { GamePhase, state } from "../state/state";
+import { sendConfiguration } from "../api/game";
+import { Ability, GameMode, Team, state } from "../state/state";
 import { Action } from "./actions";
 import { ConfigureGameChannel } from "./webrtc";
 
@@ -12,7 +12,53 @@ export class UpdatePlayerName implements Action {
 
   execute(): void {
     state.game.state.playerName = this.playerName;
-    sendToGame({ setName: this.playerName });
+    sendConfiguration({ setName: this.playerName });
+  }
+}
+
+export class SelectTeam implements Action {
+  team: Team;
+
+  constructor(team: Team) {
+    this.team = team;
+  }
+
+  execute(): void {
+    state.game.state.team = this.team;
+    sendConfiguration({ setTeam: this.team });
+  }
+}
+
+export class LeaveTeam implements Action {
+  execute(): void {
+    state.game.state.team = null;
+    sendConfiguration("leave-team");
+  }
+}
+
+export class SelectGameMode implements Action {
+  gameMode: GameMode;
+
+  constructor(gameMode: GameMode) {
+    this.gameMode = gameMode;
+  }
+
+  execute(): void {
+    state.game.state.gameMode = this.gameMode;
+    sendConfiguration({ setGameMode: this.gameMode });
+  }
+}
+
+export class SelectAbility implements Action {
+  ability: Ability;
+
+  constructor(ability: Ability) {
+    this.ability = ability;
+  }
+
+  execute(): void {
+    state.game.state.ability = this.ability;
+    sendConfiguration({ setAbility: this.ability });
   }
 }
 
@@ -21,12 +67,7 @@ export class ToggleReady implements Action {
     let isReady = !state.game.state.isReady;
     state.game.state.isReady = isReady;
     let messageToGame = isReady ? "ready" : "not-ready";
-    sendStringToGame(messageToGame);
-
-    // Since the game cannot send message back yet we consider that the game starts when this player is ready
-    if (isReady) {
-      state.game.state.phase = GamePhase.InGame;
-    }
+    sendConfiguration(messageToGame);
   }
 }
 
@@ -39,7 +80,7 @@ export class SignalUserId implements Action {
       localStorage.setItem(roomId, playerId);
     }
     state.game.playerId = playerId;
-    sendToGame({ playerId: playerId });
+    sendConfiguration({ playerId: playerId });
   }
 }
 
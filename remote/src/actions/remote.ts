@@ -1,5 +1,5 @@
-import { sendStringToGame, sendToGame } from "../api/game";
-import { state } from "../state/state";
+import { sendGameInput } from "../api/game";
+import { Feedback, PlayerState, state } from "../state/state";
 import { Action, trigger } from "./actions";
 
 export class SetControllerCenter implements Action {
@@ -37,13 +37,13 @@ export class SetControllerDirection implements Action {
         state.remote.leftController.startingPoint!.getDirectionInRadians(
           this.currentPoint
         );
-      sendToGame({ move: radianDirection });
+      sendGameInput({ move: radianDirection });
     } else {
       let radianDirection =
         state.remote.rightController.startingPoint!.getDirectionInRadians(
           this.currentPoint
         );
-      sendToGame({ aim: radianDirection });
+      sendGameInput({ aim: radianDirection });
     }
   }
 }
@@ -59,20 +59,73 @@ export class StopController implements Action {
 
   execute(): void {
     if (this.controllerId == ControllerId.MOVEMENT) {
-      sendStringToGame("stop");
+      sendGameInput("stop");
     } else {
       if (this.lastLocation !== null) {
         let radianDirection =
           state.remote.rightController.startingPoint!.getDirectionInRadians(
             this.lastLocation
           );
-        sendToGame({ aim: radianDirection });
-        sendStringToGame("shoot");
+        // The aim must arrive before the shot
+        sendGameInput({ aim: radianDirection }, true);
+        sendGameInput("shoot");
       }
     }
   }
 }
 
+export class ReloadAmmo implements Action {
+  execute(): void {
+    sendGameInput("reload");
+  }
+}
+
+export class UseAbility implements Action {
+  execute(): void {
+    sendGameInput("use-ability");
+  }
+}
+
+export class UpdatePlayerState implements Action {
+  playerState: PlayerState;
+
+  constructor(playerState: PlayerState) {
+    this.playerState = playerState;
+  }
+
+  execute(): void {
+    state.game.state.playerState = this.playerState;
+    state.game.state.phase = this.playerState.phase;
+    state.game.state.isReady = this.playerState.isReady;
+  }
+}
+
+const FLASH_DURATION_IN_MS = 150;
+
+export class ShowFeedback implements Action {
+  feedback: Feedback;
+
+  constructor(feedback: Feedback) {
+    this.feedback = feedback;
+  }
+
+  execute(): void {
+    if (navigator.vibrate) {
+      navigator.vibrate(this.feedback.vibration);
+    }
+    if (this.feedback.flashColor !== null) {
+      state.game.state.flashColor = this.feedback.flashColor;
+      setTimeout(() => trigger(new HideFlash()), FLASH_DURATION_IN_MS);
+    }
+  }
+}
+
+export class HideFlash implements Action {
+  execute(): void {
+    state.game.state.flashColor = null;
+  }
+}
+
 export class Point {
   x: number;
   y: number;
@@ -1,4 +1,10 @@
-import { SdpOffer } from "../api/game";
+import {
+  PollIceCandidates,
+  SdpOffer,
+  SendIceCandidate,
+  handleMessageFromGame,
+  sendHandshake,
+} from "../api/game";
 import { state } from "../state/state";
 import { Action, Reload, trigger } from "./actions";
 import { SignalUserId } from "./game-configuration";
@@ -14,48 +20,77 @@ export class ConfigureGameChannel implements Action {
     state.game.roomId = this.gameRoomId;
     let peerConnection = state.game.peerConnection;
     state.game.channel = peerConnection.createDataChannel("channel");
+    state.game.channel.onmessage = handleMessageFromGame;
     console.log("channel created");
 
     peerConnection.onicecandidate = (event) => {
-      console.log(
-        "onicecandidate",
-        event.candidate,
-        peerConnection.localDescription
-      );
-      if (
-        event.candidate &&
-        state.game.sdpOffer === null &&
-        hasNonLocalCandidate(peerConnection.localDescription!)
-      ) {
-        console.log("got plain offer", peerConnection.localDescription);
-        let sdpOffer = btoa(JSON.stringify(peerConnection.localDescription));
-        state.game.sdpOffer = sdpOffer;
-        console.log(sdpOffer);
-        state.pendingRequests.add(new SdpOffer(sdpOffer, this.gameRoomId));
-        trigger(new Reload());
+      console.log("onicecandidate", event.candidate);
+      if (event.candidate) {
+        trigger(new SignalIceCandidate(event.candidate.toJSON()));
       }
     };
 
+    // The candidates are trickled afterwards, no need to wait for them
     peerConnection.onnegotiationneeded = (e) =>
       peerConnection
         .createOffer()
-        .then((d) => peerConnection.setLocalDescription(d));
+        .then((d) => peerConnection.setLocalDescription(d))
+        .then(() => {
+          if (state.game.sdpOffer !== null) {
+            return;
+          }
+          let sdpOffer = btoa(JSON.stringify(peerConnection.localDescription));
+          state.game.sdpOffer = sdpOffer;
+          state.pendingRequests.add(new SdpOffer(sdpOffer, this.gameRoomId));
+          trigger(new Reload());
+        });
 
     console.log("peerConnection", peerConnection);
   }
 }
 
+/** Sends an ice candidate of the remote to the game, once the signaling session is known. */
+export class SignalIceCandidate implements Action {
+  candidate: RTCIceCandidateInit;
+
+  constructor(candidate: RTCIceCandidateInit) {
+    this.candidate = candidate;
+  }
+
+  execute(): void {
+    let sessionId = state.game.signalingSessionId;
+    if (sessionId === null) {
+      state.game.pendingIceCandidates.push(this.candidate);
+    } else {
+      state.pendingRequests.add(
+        new SendIceCandidate(this.candidate, state.game.roomId!, sessionId)
+      );
+    }
+  }
+}
+
 export class ConnectToRoom implements Action {
   sdpAnswer: string;
+  sessionId: number;
 
-  constructor(sdpAnswer: string) {
+  constructor(sdpAnswer: string, sessionId: number) {
     this.sdpAnswer = sdpAnswer;
+    this.sessionId = sessionId;
   }
 
   execute(): void {
     state.game.peerConnection.setRemoteDescription(
       new RTCSessionDescription(JSON.parse(atob(this.sdpAnswer)))
     );
+    let roomId = state.game.roomId!;
+    state.game.signalingSessionId = this.sessionId;
+    for (let candidate of state.game.pendingIceCandidates) {
+      state.pendingRequests.add(
+        new SendIceCandidate(candidate, roomId, this.sessionId)
+      );
+    }
+    state.game.pendingIceCandidates = [];
+    state.pendingRequests.add(new PollIceCandidates(roomId, this.sessionId));
     let channel = state.game.peerConnection.createDataChannel("channel");
     channel.onclose = () => {
       state.game.isChannelOpen = false;
@@ -64,38 +99,41 @@ export class ConnectToRoom implements Action {
     channel.onopen = () => {
       state.game.isChannelOpen = true;
       console.log("channel with room has opened");
+      sendHandshake();
       trigger(new SignalUserId());
     };
+    channel.onmessage = handleMessageFromGame;
     state.game.channel = channel;
+    state.game.continuousChannel =
+      state.game.peerConnection.createDataChannel("continuous-inputs", {
+        ordered: false,
+        maxRetransmits: 0,
+      });
+    state.game.continuousChannel.onmessage = handleMessageFromGame;
   }
 }
 
-function hasNonLocalCandidate(
-  sessionDescription: RTCSessionDescription
-): boolean {
-  // Get the SDP from the RTCSessionDescription
-  const sdp: string = sessionDescription.sdp;
-
-  // Split the SDP into lines to iterate through each line
-  const sdpLines: string[] = sdp.split("\r\n");
+/** Adds the ice candidates trickled by the game, and keeps polling until it is done. */
+export class AddIceCandidates implements Action {
+  candidates: RTCIceCandidateInit[];
+  done: boolean;
 
-  // Check each line to find a candidate with a non-localhost IP address
-  for (const line of sdpLines) {
-    if (line.startsWith("a=candidate")) {
-      const candidateFields: string[] = line.split(" ");
-      const ipAddress: string = candidateFields[4];
+  constructor(candidates: RTCIceCandidateInit[], done: boolean) {
+    this.candidates = candidates;
+    this.done = done;
+  }
 
-      // Check if the candidate has a non-localhost IP address
-      if (
-        ipAddress !== "0.0.0.0" &&
-        ipAddress !== "127.0.0.1" &&
-        !ipAddress.endsWith(".local")
-      ) {
-        return true;
-      }
+  execute(): void {
+    for (let candidate of this.candidates) {
+      state.game.peerConnection
+        .addIceCandidate(candidate)
+        .catch((error) => console.log("failed to add ice candidate", error));
+    }
+    if (!this.done) {
+      let sessionId = state.game.signalingSessionId!;
+      state.pendingRequests.add(
+        new PollIceCandidates(state.game.roomId!, sessionId)
+      );
     }
   }
-
-  // If no non-localhost candidate is found, return false
-  return false;
 }
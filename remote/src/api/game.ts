@@ -1,5 +1,6 @@
 import { trigger } from "../actions/actions";
-import { ConnectToRoom } from "../actions/webrtc";
+import { ShowFeedback, UpdatePlayerState } from "../actions/remote";
+import { AddIceCandidates, ConnectToRoom } from "../actions/webrtc";
 import { state } from "../state/state";
 import { HttpError, HttpMethod, HttpRequest, RequestHandler } from "./server";
 
@@ -19,28 +20,218 @@ export class SdpOffer implements RequestHandler {
       body: this.offer,
     };
   }
-  handleResponse(sdpAnswer: string): void {
-    trigger(new ConnectToRoom(sdpAnswer));
+  handleResponse(response: SdpAnswer): void {
+    trigger(new ConnectToRoom(response.answer, response["session-id"]));
   }
   handleError(error: HttpError): void {
     console.log(error);
   }
 }
 
-export function sendStringToGame(string: string) {
-  state.game.channel!.send('"' + string + '"');
+interface SdpAnswer {
+  answer: string;
+  /** Id of the signaling session used to exchange the ice candidates. */
+  "session-id": number;
 }
 
-export function sendToGame(object: object) {
-  state.game.channel!.send(stringifyObjectInKebabCase(object));
+export class SendIceCandidate implements RequestHandler {
+  candidate: RTCIceCandidateInit;
+  roomId: string;
+  sessionId: number;
+
+  constructor(
+    candidate: RTCIceCandidateInit,
+    roomId: string,
+    sessionId: number
+  ) {
+    this.candidate = candidate;
+    this.roomId = roomId;
+    this.sessionId = sessionId;
+  }
+
+  formRequest(): HttpRequest {
+    return {
+      method: HttpMethod.POST,
+      path: `/game/${this.roomId}/${this.sessionId}/candidates`,
+      body: this.candidate,
+    };
+  }
+  handleResponse(): void {}
+  handleError(error: HttpError): void {
+    console.log(error);
+  }
+}
+
+/** Waits for the ice candidates trickled by the game, until it gathered all. */
+export class PollIceCandidates implements RequestHandler {
+  roomId: string;
+  sessionId: number;
+
+  constructor(roomId: string, sessionId: number) {
+    this.roomId = roomId;
+    this.sessionId = sessionId;
+  }
+
+  formRequest(): HttpRequest {
+    return {
+      method: HttpMethod.GET,
+      path: `/game/${this.roomId}/${this.sessionId}/candidates`,
+      body: null,
+    };
+  }
+  handleResponse(response: IceCandidates): void {
+    trigger(
+      new AddIceCandidates(
+        response.candidates.map((candidate) => JSON.parse(candidate)),
+        response.done
+      )
+    );
+  }
+  handleError(error: HttpError): void {
+    console.log(error);
+  }
+}
+
+interface IceCandidates {
+  candidates: string[];
+  done: boolean;
+}
+
+/** Version of the protocol spoken with the game, the game rejects any other version. */
+const PROTOCOL_VERSION = 1;
+
+enum MessageType {
+  Handshake = "handshake",
+  Game = "game",
+  Configuration = "configuration",
+  Pong = "pong",
+}
+
+/** Codes of the game inputs in the binary encoding, see `encodeGameInput`. */
+const BINARY_INPUT_CODES: { [input: string]: number } = {
+  stop: 0,
+  shoot: 1,
+  reload: 2,
+  "use-ability": 3,
+  move: 4,
+  aim: 5,
+};
+
+export function sendHandshake() {
+  state.game.binaryInputs = false;
+  sendMessage(MessageType.Handshake, { binaryInputs: true });
+}
+
+/**
+ * Sends a game input to the game. The moves and aims go through the unordered continuous
+ * channel when it is open, unless they must arrive before the next reliable input.
+ */
+export function sendGameInput(
+  input: string | { [input: string]: number },
+  reliable: boolean = false
+) {
+  let sequence = state.game.nextSequence++;
+  let channel =
+    !reliable &&
+    typeof input !== "string" &&
+    state.game.continuousChannel?.readyState === "open"
+      ? state.game.continuousChannel
+      : state.game.channel!;
+  if (state.game.binaryInputs) {
+    channel.send(encodeGameInput(input, sequence));
+  } else {
+    sendMessage(MessageType.Game, input, sequence, channel);
+  }
 }
 
-function stringifyObjectInKebabCase(obj: any): string {
+export function sendConfiguration(input: string | object) {
+  sendMessage(MessageType.Configuration, input);
+}
+
+function sendMessage(
+  type: MessageType,
+  input?: string | number | object,
+  sequence?: number,
+  channel: RTCDataChannel = state.game.channel!
+) {
+  let message = {
+    version: PROTOCOL_VERSION,
+    sequence,
+    message: { type, input },
+  };
+  channel.send(JSON.stringify(transformObjectInKebabCase(message)));
+}
+
+/**
+ * Encodes a game input in one byte for its kind followed, for the inputs with a direction, by
+ * the direction as a little-endian 32 bits float, then by the sequence number as a
+ * little-endian 32 bits unsigned integer.
+ */
+function encodeGameInput(
+  input: string | { [input: string]: number },
+  sequence: number
+): ArrayBuffer {
+  if (typeof input === "string") {
+    let view = new DataView(new ArrayBuffer(5));
+    view.setUint8(0, BINARY_INPUT_CODES[input]);
+    view.setUint32(1, sequence, true);
+    return view.buffer;
+  }
+  let [name, direction] = Object.entries(input)[0];
+  let view = new DataView(new ArrayBuffer(9));
+  view.setUint8(0, BINARY_INPUT_CODES[name]);
+  view.setFloat32(1, direction, true);
+  view.setUint32(5, sequence, true);
+  return view.buffer;
+}
+
+export function handleMessageFromGame(event: MessageEvent) {
+  let message = parseObjectInCamelCase(JSON.parse(event.data));
+  if (message.ping !== undefined) {
+    // Answer on the channel of the ping, so the game measures its packet loss
+    sendMessage(
+      MessageType.Pong,
+      message.ping,
+      undefined,
+      event.target as RTCDataChannel
+    );
+  } else if (message.handshake) {
+    console.log(
+      "handshake with game, protocol version",
+      message.handshake.version
+    );
+    state.game.binaryInputs = message.handshake.binaryInputs === true;
+  } else if (message.playerState) {
+    trigger(new UpdatePlayerState(message.playerState));
+  } else if (message.feedback) {
+    trigger(new ShowFeedback(message.feedback));
+  } else {
+    console.log("unknown message from game", message);
+  }
+}
+
+function parseObjectInCamelCase(obj: any): any {
   if (typeof obj !== "object" || obj === null) {
-    if (typeof obj === "string") {
-      return `"${obj}"`;
+    return obj;
+  }
+
+  const result: any = Array.isArray(obj) ? [] : {};
+
+  for (const key in obj) {
+    if (Object.prototype.hasOwnProperty.call(obj, key)) {
+      const transformedKey = key.replace(/-([a-z])/g, (_, letter) =>
+        letter.toUpperCase()
+      );
+      result[transformedKey] = parseObjectInCamelCase(obj[key]);
     }
-    return String(obj);
+  }
+
+  return result;
+}
+
+function transformObjectInKebabCase(obj: any): any {
+  if (typeof obj !== "object" || obj === null) {
+    return obj;
   }
 
   const result: any = Array.isArray(obj) ? [] : {};
@@ -51,13 +242,9 @@ function stringifyObjectInKebabCase(obj: any): string {
         /[A-Z]/g,
         (match) => "-" + match.toLowerCase()
       );
-      result[`"${transformedKey}"`] = stringifyObjectInKebabCase(obj[key]);
+      result[transformedKey] = transformObjectInKebabCase(obj[key]);
     }
   }
 
-  const keyValuePairs = Object.entries(result)
-    .map(([key, value]) => `${key}:${value}`)
-    .join(",");
-
-  return `{${keyValuePairs}}`;
+  return result;
 }
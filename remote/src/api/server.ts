@@ -19,7 +19,7 @@ export class PendingRequests {
     const response = await fetch(configuration.apiBaseUrl + request.path, {
       method: request.method,
       mode: configuration.cors ? "cors" : "no-cors",
-      body: JSON.stringify(request.body),
+      body: request.body === null ? undefined : JSON.stringify(request.body),
     });
 
     if (!response.ok) {
@@ -1,17 +1,23 @@
 export default {
   rtcPeerConnection: new RTCPeerConnection({
-    iceServers: [
-      {
-        urls: [
-          "stun:stun.l.google.com:19302",
-          "stun:stun3.l.google.com:19302",
-          "stun:stun.services.mozilla.com",
-          "stun:stun.demo.cloud.webrtc.org",
+    // JSON list of STUN and TURN servers, for the networks where these ones are blocked
+    iceServers: process.env.ICE_SERVERS
+      ? JSON.parse(process.env.ICE_SERVERS)
+      : [
+          {
+            urls: [
+              "stun:stun.l.google.com:19302",
+              "stun:stun3.l.google.com:19302",
+              "stun:stun.services.mozilla.com",
+              "stun:stun.demo.cloud.webrtc.org",
+            ],
+          },
         ],
-      },
-    ],
   }),
+  // The game gives its own address when it serves the remote on the local network
   apiBaseUrl:
-    process.env.API_BASE_URL ?? "https://tank-game.flmichel.duckdns.org/api",
+    new URLSearchParams(window.location.search).get("api-base-url") ??
+    process.env.API_BASE_URL ??
+    "https://tank-game.flmichel.duckdns.org/api",
   cors: process.env.CORS === "true" ? true : false,
 };
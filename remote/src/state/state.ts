@@ -36,10 +36,20 @@ interface Register extends Request {
 
 interface Game {
   isChannelOpen: boolean;
+  /** Whether the game accepted the game inputs in the binary encoding. */
+  binaryInputs: boolean;
   roomId: string | null;
   playerId: string | null;
   channel: RTCDataChannel | null;
+  /** Unordered channel without retransmission for the moves and aims. */
+  continuousChannel: RTCDataChannel | null;
+  /** Sequence number of the next game input, so the game can drop the overtaken ones. */
+  nextSequence: number;
   sdpOffer: string | null;
+  /** Id of the signaling session given by the server with the answer of the game. */
+  signalingSessionId: number | null;
+  /** Ice candidates gathered before the signaling session was known. */
+  pendingIceCandidates: RTCIceCandidateInit[];
   peerConnection: RTCPeerConnection;
   state: GameState;
 }
@@ -48,11 +58,56 @@ export interface GameState {
   phase: GamePhase;
   playerName: string | null;
   isReady: boolean;
+  team: Team | null;
+  gameMode: GameMode;
+  ability: Ability;
+  playerState: PlayerState | null;
+  flashColor: string | null;
+}
+
+/** Personal state of the player sent by the game. */
+export interface PlayerState {
+  phase: GamePhase;
+  isReady: boolean;
+  isAlive: boolean;
+  extraLives: number;
+  roundsInMagazine: number;
+  reserveAmmo: number;
+  isReloading: boolean;
+  abilityProgress: number;
+  score: number | null;
+}
+
+/** Vibration and screen flash requested by the game. */
+export interface Feedback {
+  vibration: number[];
+  flashColor: string | null;
+}
+
+export enum GameMode {
+  LastTankStanding = "last-tank-standing",
+  TimedDeathmatch = "timed-deathmatch",
+  TeamDeathmatch = "team-deathmatch",
+  CaptureTheFlag = "capture-the-flag",
+  KingOfTheHill = "king-of-the-hill",
+}
+
+export enum Ability {
+  Mine = "mine",
+  Dash = "dash",
+  Shield = "shield",
+  Smoke = "smoke",
+}
+
+export enum Team {
+  Red = "red",
+  Blue = "blue",
 }
 
 export enum GamePhase {
-  BeforeNextGame,
-  InGame,
+  BeforeNextGame = "before-next-game",
+  InGame = "in-game",
+  BreakInGame = "break-in-game",
 }
 
 interface Remote {
@@ -80,15 +135,25 @@ export let state: State = {
   },
   game: {
     isChannelOpen: false,
+    binaryInputs: false,
     roomId: null,
     playerId: null,
     channel: null,
+    continuousChannel: null,
+    nextSequence: 1,
     sdpOffer: null,
+    signalingSessionId: null,
+    pendingIceCandidates: [],
     peerConnection: configuration.rtcPeerConnection,
     state: {
       phase: GamePhase.BeforeNextGame,
       playerName: null,
       isReady: false,
+      team: null,
+      gameMode: GameMode.LastTankStanding,
+      ability: Ability.Mine,
+      playerState: null,
+      flashColor: null,
     },
   },
   displaySettings: {
@@ -15,6 +15,7 @@ export class View extends LitElement {
   render() {
     switch (this.state.gameState.phase) {
       case GamePhase.BeforeNextGame:
+      case GamePhase.BreakInGame:
         return html`
           <nav-bar></nav-bar>
           <game-configuration
@@ -1,9 +1,19 @@
 import { LitElement, html, css } from "lit-element";
 import { customElement, property } from "lit/decorators.js";
 import { CanvasData, ControllerId } from "../../actions/remote";
-import { DisplaySettings, GameState } from "../../state/state";
+import {
+  Ability,
+  DisplaySettings,
+  GameMode,
+  GameState,
+  Team,
+} from "../../state/state";
 import { trigger } from "../../actions/actions";
 import {
+  LeaveTeam,
+  SelectAbility,
+  SelectGameMode,
+  SelectTeam,
   ToggleReady,
   UpdatePlayerName,
 } from "../../actions/game-configuration";
@@ -22,6 +32,36 @@ export class CanvasTouch extends LitElement {
         @input=${(event: any) =>
           trigger(new UpdatePlayerName(event.target.value))}
       />
+      <select
+        @change=${(event: any) =>
+          trigger(new SelectGameMode(event.target.value))}
+      >
+        <option value=${GameMode.LastTankStanding}>Last tank standing</option>
+        <option value=${GameMode.TimedDeathmatch}>Timed deathmatch</option>
+        <option value=${GameMode.TeamDeathmatch}>Team deathmatch</option>
+        <option value=${GameMode.CaptureTheFlag}>Capture the flag</option>
+        <option value=${GameMode.KingOfTheHill}>King of the hill</option>
+      </select>
+      <select
+        @change=${(event: any) =>
+          trigger(new SelectAbility(event.target.value))}
+      >
+        <option value=${Ability.Mine}>Mine</option>
+        <option value=${Ability.Dash}>Dash</option>
+        <option value=${Ability.Shield}>Shield</option>
+        <option value=${Ability.Smoke}>Smoke</option>
+      </select>
+      <button @click=${() => trigger(new SelectTeam(Team.Red))}>
+        ${this.state.team === Team.Red ? "In red team" : "Join red team"}
+      </button>
+      <button @click=${() => trigger(new SelectTeam(Team.Blue))}>
+        ${this.state.team === Team.Blue ? "In blue team" : "Join blue team"}
+      </button>
+      ${this.state.team !== null
+        ? html`<button @click=${() => trigger(new LeaveTeam())}>
+            Leave team
+          </button>`
+        : html``}
       <button @click=${() => trigger(new ToggleReady())}>
         ${this.state.isReady ? "Ready" : "Not Ready"}
       </button>
@@ -1,7 +1,13 @@
 import { LitElement, html, css } from "lit-element";
 import { customElement, property } from "lit/decorators.js";
-import { CanvasData, ControllerId } from "../../actions/remote";
+import {
+  CanvasData,
+  ControllerId,
+  ReloadAmmo,
+  UseAbility,
+} from "../../actions/remote";
 import { DisplayState } from "../../state/displayState";
+import { trigger } from "../../actions/actions";
 
 @customElement("game-remove")
 export class GameRemote extends LitElement {
@@ -13,6 +19,33 @@ export class GameRemote extends LitElement {
       canvas {
         border: 1px solid black;
       }
+      #reload {
+        position: absolute;
+        top: 10px;
+        right: 10px;
+        font-size: 20px;
+      }
+      #ability {
+        position: absolute;
+        bottom: 10px;
+        right: 10px;
+        font-size: 20px;
+      }
+      #flash {
+        position: absolute;
+        top: 0;
+        left: 0;
+        width: 100%;
+        height: 100%;
+        opacity: 0.5;
+        pointer-events: none;
+      }
+      #status {
+        position: absolute;
+        top: 10px;
+        left: 10px;
+        font-size: 16px;
+      }
     `;
   }
 
@@ -35,6 +68,38 @@ export class GameRemote extends LitElement {
         width=${this.state.displaySettings.windowWidth / 2}
         height=${this.state.displaySettings.windowHeight}
       ></canvas>
+      <button id="reload" @click=${() => trigger(new ReloadAmmo())}>
+        Reload
+      </button>
+      <button id="ability" @click=${() => trigger(new UseAbility())}>
+        Ability
+      </button>
+      ${this.renderStatus()}
+      ${this.state.gameState.flashColor !== null
+        ? html`<div
+            id="flash"
+            style="background-color: ${this.state.gameState.flashColor}"
+          ></div>`
+        : html``}
+    </div>`;
+  }
+
+  renderStatus() {
+    let playerState = this.state.gameState.playerState;
+    if (playerState === null) {
+      return html``;
+    }
+    let ammo = playerState.isReloading
+      ? "reloading..."
+      : `${playerState.roundsInMagazine} / ${playerState.reserveAmmo}`;
+    return html` <div id="status">
+      <div>${playerState.isAlive ? "Alive" : "Dead"}</div>
+      <div>Extra lives: ${playerState.extraLives}</div>
+      <div>Ammo: ${ammo}</div>
+      <div>Ability: ${Math.round(playerState.abilityProgress * 100)}%</div>
+      ${playerState.score !== null
+        ? html`<div>Score: ${playerState.score}</div>`
+        : html``}
     </div>`;
   }
 
@@ -23,6 +23,19 @@ pub struct ApplicationSettings {
     host: String,
     web_port: u16,
     games_port: u16,
+    #[serde(default)]
+    ice_servers: Vec<IceServerSettings>,
+}
+
+/// STUN or TURN server sent to the game rooms when they register, the TURN servers need a
+/// username and a credential.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct IceServerSettings {
+    urls: Vec<String>,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    credential: String,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -70,4 +83,8 @@ impl ApplicationSettings {
     pub fn get_web_path(&self) -> String {
         format!("{}:{}", self.host, self.web_port)
     }
+
+    pub fn get_ice_servers(&self) -> &[IceServerSettings] {
+        &self.ice_servers
+    }
 }
@@ -1,12 +1,17 @@
 use crate::{
-    configuration::ApplicationSettings,
+    configuration::{ApplicationSettings, IceServerSettings},
     result::{
         Error,
         ErrorKind::{ConfigurationError, NetworkError, ParsingError},
     },
 };
 
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use futures_channel::mpsc::{unbounded, UnboundedSender};
 use futures_util::{
@@ -18,20 +23,81 @@ use futures_util::{
 use serde::{de, Deserialize, Serialize};
 use tokio::{
     net::{TcpListener, TcpStream},
-    sync::mpsc::Sender,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender as ReplySender},
     sync::Mutex,
 };
-use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
-use tracing::{debug, error, info};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        handshake::server::{Request, Response},
+        Message,
+    },
+    WebSocketStream,
+};
+use tracing::{debug, error, info, warn};
 
-pub type Tx = UnboundedSender<SdpOffer>;
+pub type Tx = UnboundedSender<RoomRequest>;
 pub type RoomMap = Arc<Mutex<HashMap<String, Tx>>>;
+/// Replies of the game rooms not yet polled by the remotes, by signaling session id.
+pub type SignalingSessions = Arc<Mutex<HashMap<u32, Arc<Mutex<SignalingSession>>>>>;
+
+/// Offers sent to a game room and waiting for its replies, by signaling session id.
+type RequestMap = Arc<std::sync::Mutex<HashMap<u32, PendingRequest>>>;
+/// Rooms which their game can get back after a reconnection, by room id.
+type ReclaimableRooms = Arc<Mutex<HashMap<String, ReclaimableRoom>>>;
+
+/// Time during which a disconnected game can reclaim its room.
+const RECLAIM_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Time after which a signaling session is dropped, when its remote stops polling the
+/// candidates or its game room never sends the last one.
+pub const SIGNALING_TIMEOUT: Duration = Duration::from_secs(2 * 60);
 
-type RequestMap = Arc<std::sync::Mutex<HashMap<u32, Sender<String>>>>;
+struct ReclaimableRoom {
+    token: String,
+    /// `None` while the game is connected.
+    disconnected_at: Option<Instant>,
+}
+
+/// Signaling request of a remote, forwarded to its game room.
+pub enum RoomRequest {
+    Offer(SdpOffer),
+    IceCandidate(IceCandidateMessage),
+}
 
 pub struct SdpOffer {
+    /// Id of the signaling session, used by the remote to exchange the ice candidates.
+    pub id: u32,
     pub offer: String,
-    pub return_channel: Sender<String>,
+    pub return_channel: ReplySender<SignalingReply>,
+}
+
+/// Signaling session of a remote, which polls the candidates trickled by its game room.
+pub struct SignalingSession {
+    /// Candidates of the game room received before its answer.
+    pub early_candidates: Vec<String>,
+    pub replies: UnboundedReceiver<SignalingReply>,
+    /// The session expires when the remote stops polling.
+    pub last_poll: Instant,
+}
+
+impl SignalingSession {
+    pub fn is_expired(&self) -> bool {
+        self.last_poll.elapsed() >= SIGNALING_TIMEOUT
+    }
+}
+
+struct PendingRequest {
+    return_channel: ReplySender<SignalingReply>,
+    sent_at: Instant,
+}
+
+/// Signaling reply of a game room, the answer followed by the trickled ice candidates.
+pub enum SignalingReply {
+    Answer(String),
+    /// The game room rejected the offer, no candidate follows.
+    Error(String),
+    /// `None` once the game room gathered all its candidates.
+    IceCandidate(Option<String>),
 }
 
 #[derive(Serialize)]
@@ -40,6 +106,23 @@ pub struct SdpOffer {
 pub enum GameMessage {
     RoomId(String),
     SdpOffer(SdpMessage),
+    IceServers(IceServersMessage),
+    ReclaimToken(ReclaimTokenMessage),
+}
+
+/// Secret sent with the room id, the game gives it back to reclaim its room when it
+/// reconnects.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReclaimTokenMessage {
+    pub reclaim_token: String,
+}
+
+/// STUN and TURN servers the game room can use in addition to its own ones.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IceServersMessage {
+    pub ice_servers: Vec<IceServerSettings>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,11 +132,39 @@ pub struct SdpMessage {
     pub id: u32,
 }
 
+/// Ice candidate exchanged between a remote and a game room, for the signaling session `id`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IceCandidateMessage {
+    pub id: u32,
+    pub candidate: Option<String>,
+}
+
+/// Sent by a game room instead of the answer when the offer is invalid.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SdpErrorMessage {
+    id: u32,
+    error: String,
+}
+
+/// Signaling message sent by a game room. The error is tried before the candidate, which
+/// would accept it as a last candidate.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GameRoomMessage {
+    SdpAnswer(SdpMessage),
+    SdpError(SdpErrorMessage),
+    IceCandidate(IceCandidateMessage),
+}
+
 pub async fn start_game_application(
     room_map: RoomMap,
     settings: &ApplicationSettings,
 ) -> Result<(), Error> {
     let game_path = settings.get_game_path();
+    let ice_servers = settings.get_ice_servers().to_vec();
+    let reclaimable_rooms = ReclaimableRooms::default();
     // Create the event loop and TCP listener we'll accept connections on.
     let listener = TcpListener::bind(&game_path).await.map_err(|err| {
         Error::from(err, ConfigurationError).explain("failed to create tcp listener")
@@ -65,8 +176,10 @@ pub async fn start_game_application(
     while let Ok((stream, game_room_address)) = listener.accept().await {
         tokio::spawn(handle_connection(
             room_map.clone(),
+            reclaimable_rooms.clone(),
             stream,
             game_room_address,
+            ice_servers.clone(),
         ));
     }
     Ok(())
@@ -74,17 +187,25 @@ pub async fn start_game_application(
 
 async fn handle_connection(
     room_map: RoomMap,
+    reclaimable_rooms: ReclaimableRooms,
     raw_stream: TcpStream,
     game_room_address: SocketAddr,
+    ice_servers: Vec<IceServerSettings>,
 ) {
-    let id = generate_id();
-
     debug!(
         "Incoming TCP connection from the address {}",
         game_room_address
     );
 
-    let mut ws_stream = tokio_tungstenite::accept_async(raw_stream)
+    // The game gives its previous room in the query to reclaim it
+    let mut query = None;
+    // The error type is imposed by tungstenite
+    #[allow(clippy::result_large_err)]
+    let read_query = |request: &Request, response: Response| {
+        query = request.uri().query().map(ToOwned::to_owned);
+        Ok(response)
+    };
+    let mut ws_stream = tokio_tungstenite::accept_hdr_async(raw_stream, read_query)
         .await
         .expect("Error during the websocket handshake occurred");
     debug!(
@@ -92,21 +213,41 @@ async fn handle_connection(
         game_room_address
     );
 
+    let (id, reclaim_token) = claim_room(&reclaimable_rooms, query.as_deref()).await;
+
     let (message_sender, message_receiver) = unbounded();
-    room_map.lock().await.insert(id.clone(), message_sender);
+    room_map
+        .lock()
+        .await
+        .insert(id.clone(), message_sender.clone());
 
     if let Err(err) = send_id_to_game_room(&mut ws_stream, id.clone()).await {
         error!(
             "failed to send id to game room with address {} ({:?}), stopping websocket connection",
             game_room_address, err
         );
+        release_room(&room_map, &reclaimable_rooms, &id, &message_sender).await;
         return;
     }
 
+    let mut messages = vec![GameMessage::ReclaimToken(ReclaimTokenMessage {
+        reclaim_token,
+    })];
+    if !ice_servers.is_empty() {
+        messages.push(GameMessage::IceServers(IceServersMessage { ice_servers }));
+    }
+    for message in messages {
+        if let Err(err) = send_message_to_game_room(&mut ws_stream, &message).await {
+            error!(
+                "failed to send message to game room with address {} ({:?})",
+                game_room_address, err
+            );
+        }
+    }
+
     let (outgoing, incoming) = ws_stream.split();
 
     let request_map = RequestMap::new(std::sync::Mutex::new(HashMap::new()));
-    let mut request_counter = 0;
 
     let send_sdp_answer = incoming.try_for_each(|message| {
         handle_game_message(message, game_room_address, request_map.clone())
@@ -114,17 +255,27 @@ async fn handle_connection(
 
     let receive_sdp_offers = message_receiver
         .map(|request| {
-            request_map
-                .lock()
-                .unwrap()
-                .insert(request_counter, request.return_channel);
-            let offer_message = SdpMessage {
-                data: request.offer,
-                id: request_counter,
+            let message = match request {
+                RoomRequest::Offer(offer) => {
+                    // The offers of the remotes which never got all their candidates expire
+                    let mut pending_requests = request_map.lock().unwrap();
+                    pending_requests
+                        .retain(|_, request| request.sent_at.elapsed() < SIGNALING_TIMEOUT);
+                    pending_requests.insert(
+                        offer.id,
+                        PendingRequest {
+                            return_channel: offer.return_channel,
+                            sent_at: Instant::now(),
+                        },
+                    );
+                    serde_json::to_string(&SdpMessage {
+                        data: offer.offer,
+                        id: offer.id,
+                    })
+                }
+                RoomRequest::IceCandidate(candidate) => serde_json::to_string(&candidate),
             };
-            let offer_message = serde_json::to_string(&offer_message).unwrap();
-            request_counter += 1;
-            Ok(Message::text(offer_message))
+            Ok(Message::text(message.unwrap()))
         })
         .forward(outgoing);
 
@@ -135,7 +286,74 @@ async fn handle_connection(
         "the room {} from the address {} was disconnected",
         &id, &game_room_address
     );
-    room_map.lock().await.remove(&id);
+    release_room(&room_map, &reclaimable_rooms, &id, &message_sender).await;
+}
+
+/// Gives its room back to a game which reconnects with the right reclaim token, otherwise
+/// creates a new room. Returns the id and the reclaim token of the room.
+async fn claim_room(reclaimable_rooms: &ReclaimableRooms, query: Option<&str>) -> (String, String) {
+    let mut reclaimable_rooms = reclaimable_rooms.lock().await;
+    reclaimable_rooms.retain(|_, room| {
+        room.disconnected_at
+            .is_none_or(|disconnected_at| disconnected_at.elapsed() < RECLAIM_TIMEOUT)
+    });
+
+    if let Some((id, token)) = query.and_then(parse_reclaim_query) {
+        match reclaimable_rooms.get_mut(&id) {
+            // The previous connection of the game may not be closed yet
+            Some(room) if room.token == token => {
+                info!("the room {} was reclaimed by its game", id);
+                room.disconnected_at = None;
+                return (id, token);
+            }
+            _ => info!("the room {} could not be reclaimed", id),
+        }
+    }
+
+    let id = generate_id();
+    let token = generate_id();
+    reclaimable_rooms.insert(
+        id.clone(),
+        ReclaimableRoom {
+            token: token.clone(),
+            disconnected_at: None,
+        },
+    );
+    (id, token)
+}
+
+/// Removes the room of a disconnected game, unless the game already reclaimed it with a new
+/// connection.
+async fn release_room(
+    room_map: &RoomMap,
+    reclaimable_rooms: &ReclaimableRooms,
+    id: &str,
+    message_sender: &Tx,
+) {
+    let mut room_map = room_map.lock().await;
+    if !room_map
+        .get(id)
+        .is_some_and(|sender| sender.same_receiver(message_sender))
+    {
+        return;
+    }
+    room_map.remove(id);
+    if let Some(room) = reclaimable_rooms.lock().await.get_mut(id) {
+        room.disconnected_at = Some(Instant::now());
+    }
+}
+
+fn parse_reclaim_query(query: &str) -> Option<(String, String)> {
+    let mut id = None;
+    let mut token = None;
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match key {
+            "room-id" => id = Some(value.to_owned()),
+            "reclaim-token" => token = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    Some((id?, token?))
 }
 
 fn generate_id() -> String {
@@ -161,6 +379,20 @@ async fn send_id_to_game_room(
         })
 }
 
+async fn send_message_to_game_room(
+    ws_stream: &mut WebSocketStream<TcpStream>,
+    message: &GameMessage,
+) -> Result<(), Error> {
+    ws_stream
+        .send(Message::text(serde_json::to_string(message).map_err(
+            |err| Error::from(err, ParsingError).explain("failed to parse the Game Message"),
+        )?))
+        .await
+        .map_err(|err| {
+            Error::from(err, NetworkError).explain("failed send message to the game room")
+        })
+}
+
 fn parse_message<'a, T>(message: &'a Message) -> Result<T, Error>
 where
     T: de::Deserialize<'a>,
@@ -177,22 +409,56 @@ where
     })
 }
 
-fn handle_game_message<E>(
+/// Forwards the answers and the candidates of a game room to their remote. The keepalive
+/// frames of the game are ignored, a close frame ends the connection.
+fn handle_game_message(
     message: Message,
     game_room_address: SocketAddr,
     request_map: RequestMap,
-) -> Ready<Result<(), E>> {
+) -> Ready<Result<(), tungstenite::Error>> {
+    match message {
+        Message::Text(_) => {}
+        Message::Close(_) => return future::err(tungstenite::Error::ConnectionClosed),
+        Message::Ping(_) | Message::Pong(_) | Message::Binary(_) | Message::Frame(_) => {
+            return future::ok(())
+        }
+    }
     debug!(
-        "Received a sdp answer from {}: {}",
+        "Received a signaling message from {}: {}",
         game_room_address, message
     );
-    let sdp_answer: SdpMessage = parse_message(&message).unwrap();
-    request_map
-        .lock()
-        .unwrap()
-        .get(&sdp_answer.id)
-        .unwrap()
-        .try_send(sdp_answer.data)
-        .unwrap();
+    let (id, reply) = match parse_message(&message) {
+        Ok(GameRoomMessage::SdpAnswer(sdp_answer)) => {
+            (sdp_answer.id, SignalingReply::Answer(sdp_answer.data))
+        }
+        Ok(GameRoomMessage::SdpError(sdp_error)) => {
+            (sdp_error.id, SignalingReply::Error(sdp_error.error))
+        }
+        Ok(GameRoomMessage::IceCandidate(candidate)) => (
+            candidate.id,
+            SignalingReply::IceCandidate(candidate.candidate),
+        ),
+        Err(err) => {
+            warn!(
+                "ignoring invalid message from game room with address {} ({:?})",
+                game_room_address, err
+            );
+            return future::ok(());
+        }
+    };
+    let mut request_map = request_map.lock().unwrap();
+    let mut is_last_reply = matches!(
+        reply,
+        SignalingReply::IceCandidate(None) | SignalingReply::Error(_)
+    );
+    if let Some(request) = request_map.get(&id) {
+        if request.return_channel.send(reply).is_err() {
+            debug!("the remote of the signaling session {} is gone", id);
+            is_last_reply = true;
+        }
+    }
+    if is_last_reply {
+        request_map.remove(&id);
+    }
     future::ok(())
 }
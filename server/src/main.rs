@@ -1,7 +1,7 @@
 use server::startup::setup_logger;
 use server::{configuration::get_configuration, startup::get_database_pool};
 use server::{
-    games_api::{start_game_application, RoomMap},
+    games_api::{start_game_application, RoomMap, SignalingSessions},
     startup::start_web_application,
 };
 use std::collections::HashMap;
@@ -30,7 +30,14 @@ async fn main() {
             .expect("could not start websocket");
     });
 
-    start_web_application(database_pool, room_map, &settings.application)
-        .await
-        .expect("failed to start server");
+    let signaling_sessions = SignalingSessions::new(Mutex::new(HashMap::new()));
+
+    start_web_application(
+        database_pool,
+        room_map,
+        signaling_sessions,
+        &settings.application,
+    )
+    .await
+    .expect("failed to start server");
 }
@@ -1,16 +1,50 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
+    Extension,
 };
 
-use tokio::sync::mpsc;
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::{timeout, timeout_at},
+};
 use tracing::debug;
 
-use crate::games_api::{RoomMap, SdpOffer};
+use crate::games_api::{
+    IceCandidateMessage, RoomMap, RoomRequest, SdpOffer, SignalingReply, SignalingSession,
+    SignalingSessions,
+};
+
+/// Maximum time a poll of the ice candidates waits for a new candidate of the game room.
+const CANDIDATES_POLL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum time the remote waits for the answer of the game room to its offer.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct SdpAnswer {
+    answer: String,
+    session_id: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct IceCandidates {
+    candidates: Vec<String>,
+    /// The game room gathered all its candidates, the remote can stop polling.
+    done: bool,
+}
 
 pub async fn post_sdp_session(
     State(room_map): State<RoomMap>,
+    Extension(sessions): Extension<SignalingSessions>,
     Path(id): Path<String>,
     body: String,
 ) -> impl IntoResponse {
@@ -18,21 +52,124 @@ pub async fn post_sdp_session(
     debug!("received spd offer for room id {}", id);
 
     if let Some(tx_game) = room_map.lock().await.get(&id) {
-        let (tx, mut rx) = mpsc::channel(1);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session_id = rand::random::<u32>();
         let request = SdpOffer {
+            id: session_id,
             offer: body,
-            return_channel: tx.clone(),
+            return_channel: tx,
         };
         debug!("send offer to game");
-        tx_game.unbounded_send(request).unwrap();
-        match rx.recv().await {
-            Some(description) => (StatusCode::OK, serde_json::to_string(&description).unwrap()),
-            None => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "problem with channel".to_owned(),
-            ),
+        tx_game.unbounded_send(RoomRequest::Offer(request)).unwrap();
+        // The game room can gather its first candidates before sending its answer
+        let mut early_candidates = Vec::new();
+        let deadline = (Instant::now() + ANSWER_TIMEOUT).into();
+        loop {
+            let Ok(reply) = timeout_at(deadline, rx.recv()).await else {
+                return (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    "the game room did not answer".to_owned(),
+                );
+            };
+            match reply {
+                Some(SignalingReply::IceCandidate(Some(candidate))) => {
+                    early_candidates.push(candidate)
+                }
+                Some(SignalingReply::IceCandidate(None)) => {}
+                Some(SignalingReply::Answer(answer)) => {
+                    // The next candidates of the game room are polled by the remote
+                    let session = SignalingSession {
+                        early_candidates,
+                        replies: rx,
+                        last_poll: Instant::now(),
+                    };
+                    let mut sessions = sessions.lock().await;
+                    // The sessions being polled are locked, they are not expired
+                    sessions.retain(|_, session| {
+                        session
+                            .try_lock()
+                            .map_or(true, |session| !session.is_expired())
+                    });
+                    sessions.insert(session_id, Arc::new(Mutex::new(session)));
+                    let answer = SdpAnswer { answer, session_id };
+                    return (StatusCode::OK, serde_json::to_string(&answer).unwrap());
+                }
+                Some(SignalingReply::Error(error)) => return (StatusCode::BAD_REQUEST, error),
+                None => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "problem with channel".to_owned(),
+                    )
+                }
+            }
         }
     } else {
         (StatusCode::NOT_FOUND, "room not found".to_owned())
     }
 }
+
+/// Forwards an ice candidate of the remote to the game room.
+pub async fn post_ice_candidate(
+    State(room_map): State<RoomMap>,
+    Path((id, session_id)): Path<(String, u32)>,
+    body: String,
+) -> impl IntoResponse {
+    debug!("received ice candidate for room id {}", id);
+
+    if let Some(tx_game) = room_map.lock().await.get(&id) {
+        let candidate = IceCandidateMessage {
+            id: session_id,
+            candidate: Some(body),
+        };
+        tx_game
+            .unbounded_send(RoomRequest::IceCandidate(candidate))
+            .unwrap();
+        (StatusCode::OK, "{}".to_owned())
+    } else {
+        (StatusCode::NOT_FOUND, "room not found".to_owned())
+    }
+}
+
+/// Returns the ice candidates of the game room gathered since the last poll, waiting for the
+/// next one when there is none yet.
+pub async fn get_ice_candidates(
+    Extension(sessions): Extension<SignalingSessions>,
+    Path((_, session_id)): Path<(String, u32)>,
+) -> impl IntoResponse {
+    let Some(session) = sessions.lock().await.get(&session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "session not found".to_owned());
+    };
+    let mut session = session.lock().await;
+    session.last_poll = Instant::now();
+
+    let mut candidates = IceCandidates {
+        candidates: std::mem::take(&mut session.early_candidates),
+        done: false,
+    };
+    let mut next_reply = if !candidates.candidates.is_empty() {
+        session.replies.try_recv().ok()
+    } else {
+        match timeout(CANDIDATES_POLL_TIMEOUT, session.replies.recv()).await {
+            Ok(Some(reply)) => Some(reply),
+            // The game room is gone
+            Ok(None) => {
+                candidates.done = true;
+                None
+            }
+            Err(_) => None,
+        }
+    };
+    while let Some(reply) = next_reply {
+        match reply {
+            SignalingReply::IceCandidate(Some(candidate)) => candidates.candidates.push(candidate),
+            SignalingReply::IceCandidate(None) => candidates.done = true,
+            SignalingReply::Answer(_) | SignalingReply::Error(_) => {}
+        }
+        next_reply = session.replies.try_recv().ok();
+    }
+
+    if candidates.done {
+        sessions.lock().await.remove(&session_id);
+    }
+    (StatusCode::OK, serde_json::to_string(&candidates).unwrap())
+}
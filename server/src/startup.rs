@@ -10,11 +10,11 @@ use tracing::{info, Level};
 
 use crate::{
     configuration::{ApplicationSettings, DatabaseSettings},
-    games_api::RoomMap,
+    games_api::{RoomMap, SignalingSessions},
     repository::{Account, Repo},
     result::{Error, ErrorKind::*},
     route::{
-        login, post_sdp_session,
+        get_ice_candidates, login, post_ice_candidate, post_sdp_session,
         registration::{create_user, is_email_available, is_name_available},
     },
 };
@@ -34,6 +34,7 @@ pub async fn get_database_pool(settings: &DatabaseSettings) -> Result<Pool<Postg
 pub async fn start_web_application(
     database_pool: Pool<Postgres>,
     room_map: RoomMap,
+    signaling_sessions: SignalingSessions,
     settings: &ApplicationSettings,
 ) -> Result<(), Error> {
     let app = Router::new()
@@ -42,8 +43,13 @@ pub async fn start_web_application(
         .route("/api/user/{name}", get(is_name_available))
         .route("/api/user/email/{email}}", get(is_email_available))
         .route("/api/game/:id", post(post_sdp_session))
+        .route(
+            "/api/game/:id/:session_id/candidates",
+            get(get_ice_candidates).post(post_ice_candidate),
+        )
         .layer(CorsLayer::permissive())
         .layer(Extension(Repo::<Account>::new(database_pool)))
+        .layer(Extension(signaling_sessions))
         .with_state(room_map);
 
     let addr_str = settings.get_web_path();